@@ -1,47 +1,284 @@
 use crate::{
 	command::CommandPool,
+	command::{AccessFlags, PipelineStageFlags},
 	device::{Device, Queue, SubmitFuture},
+	error::{Error, Result},
+	image::{texel_block, Format, ImageAspectFlags},
+	physical_device::FormatFeatureFlags,
+	sync::GpuFuture,
 };
-use ash::{version::DeviceV1_0, vk};
-use std::{marker::PhantomData, mem::size_of, slice, sync::Arc};
-use typenum::{Bit, B1};
+use ash::{
+	version::{DeviceV1_0, DeviceV1_2},
+	vk,
+};
+use std::{
+	marker::PhantomData,
+	mem::{align_of, size_of},
+	ops::{Bound, Deref, DerefMut, Range, RangeBounds},
+	slice,
+	sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+use log::error;
+use nalgebra::{ArrayStorage, Matrix, Point, Scalar, U1, U2, U3, U4};
 use vk::BufferUsageFlags;
-use vk_mem::{Allocation, AllocationCreateInfo, MemoryUsage};
+use vk_mem::{Allocation, AllocationCreateFlags, AllocationCreateInfo, MemoryUsage};
 
 pub struct Buffer<T: ?Sized> {
 	device: Arc<Device>,
 	pub vk: vk::Buffer,
 	alloc: Allocation,
 	size: u64,
+	usage: BufferUsageFlags,
+	// null unless the buffer is host-visible, in which case it stays mapped for its whole lifetime
+	mapped: MappedPtr,
+	coherent: bool,
+	map_lock: RwLock<()>,
 	phantom: PhantomData<T>,
 }
 impl<T: ?Sized> Buffer<T> {
+	/// Fails with `Error::MissingUsage` unless the buffer was created with `SHADER_DEVICE_ADDRESS` usage. Uses
+	/// `VK_KHR_buffer_device_address` where it is enabled, and Vulkan 1.2 otherwise.
+	pub fn device_address(self: &Arc<Self>) -> Result<DeviceAddress<T>> {
+		if !self.usage.contains(BufferUsageFlags::SHADER_DEVICE_ADDRESS) {
+			return Err(Error::MissingUsage("SHADER_DEVICE_ADDRESS"));
+		}
+
+		let info = vk::BufferDeviceAddressInfo::builder().buffer(self.vk);
+		let address = match &self.device.khr_buffer_device_address {
+			Some(khr) => unsafe { khr.get_buffer_device_address_khr(self.device.vk.handle(), &*info) },
+			None => unsafe { self.device.vk.get_buffer_device_address(&info) },
+		};
+		Ok(DeviceAddress { buffer: self.clone(), address })
+	}
+
+	/// Whether `map` and `map_mut` can be used.
+	pub fn is_host_visible(&self) -> bool {
+		!self.mapped.0.is_null()
+	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
+	}
+
 	pub fn size(&self) -> u64 {
 		self.size
 	}
+
+	pub fn usage(&self) -> BufferUsageFlags {
+		self.usage
+	}
+
+	/// `SHADER_DEVICE_ADDRESS` usage requires the `buffer_device_address` feature, see
+	/// `Device::is_buffer_device_address_enabled`.
+	pub(crate) fn new(
+		device: Arc<Device>,
+		size: u64,
+		usage: BufferUsageFlags,
+		memory_usage: MemoryUsage,
+	) -> Result<Arc<Self>> {
+		if usage.contains(BufferUsageFlags::SHADER_DEVICE_ADDRESS)
+			&& !device.is_buffer_device_address_enabled()
+		{
+			return Err(Error::MissingFeatures(vec!["buffer_device_address"]));
+		}
+
+		let ci = vk::BufferCreateInfo::builder().size(size).usage(usage).build();
+
+		let flags = match memory_usage {
+			MemoryUsage::GpuOnly => AllocationCreateFlags::NONE,
+			_ => AllocationCreateFlags::MAPPED,
+		};
+		let aci = AllocationCreateInfo { usage: memory_usage, flags, ..Default::default() };
+
+		device.check_lost()?;
+		let allocator = &device.allocator;
+		let (vk, alloc, info) = device.check(allocator.create_buffer(&ci, &aci))?;
+		let props = allocator.get_memory_type_properties(info.get_memory_type());
+		let props = device.check(props).inspect_err(|_| {
+			let _ = allocator.destroy_buffer(vk, &alloc);
+		})?;
+
+		let mapped = MappedPtr(info.get_mapped_data());
+		let coherent = props.contains(vk::MemoryPropertyFlags::HOST_COHERENT);
+		let map_lock = RwLock::new(());
+		Ok(Arc::new(Self { device, vk, alloc, size, usage, mapped, coherent, map_lock, phantom: PhantomData }))
+	}
+
+	/// Copies the buffer into a new `GpuToCpu` buffer on `queue` and blocks until it can be read by the host.
+	fn download_buffer(self: &Arc<Self>, queue: &Arc<Queue>, pool: &Arc<CommandPool>) -> Result<Arc<Self>>
+	where
+		T: Send + Sync + 'static,
+	{
+		let usage = BufferUsageFlags::TRANSFER_DST;
+		let readback = Self::new(self.device.clone(), self.size, usage, GpuToCpu::MEMORY_USAGE)?;
+
+		let cmd = pool
+			.record(true, false)?
+			.memory_barrier(
+				PipelineStageFlags::ALL_COMMANDS,
+				PipelineStageFlags::TRANSFER,
+				AccessFlags::MEMORY_WRITE,
+				AccessFlags::TRANSFER_READ,
+			)
+			.copy_buffer(self.clone(), readback.clone())
+			.memory_barrier(
+				PipelineStageFlags::TRANSFER,
+				PipelineStageFlags::HOST,
+				AccessFlags::TRANSFER_WRITE,
+				AccessFlags::HOST_READ,
+			)
+			.build()?;
+		queue.submit(cmd).then_signal_fence()?.wait()?;
+
+		Ok(readback)
+	}
+
+	/// Makes host writes to `size` bytes at `offset` of non-coherent memory visible to the device.
+	fn flush(&self, offset: u64, size: u64) -> Result<()> {
+		if !self.coherent && size > 0 {
+			let res = self.device.allocator.flush_allocation(&self.alloc, offset as _, size as _);
+			self.device.check(res)?;
+		}
+		Ok(())
+	}
+
+	/// Makes device writes to `size` bytes at `offset` of non-coherent memory visible to the host.
+	fn invalidate(&self, offset: u64, size: u64) -> Result<()> {
+		if !self.coherent && size > 0 {
+			let res = self.device.allocator.invalidate_allocation(&self.alloc, offset as _, size as _);
+			self.device.check(res)?;
+		}
+		Ok(())
+	}
+
+	fn lock_read(&self, offset: u64, size: u64) -> Result<RwLockReadGuard<'_, ()>> {
+		if !self.is_host_visible() {
+			return Err(Error::NotHostVisible);
+		}
+		let lock = self.map_lock.read().unwrap();
+		self.invalidate(offset, size)?;
+		Ok(lock)
+	}
+
+	fn lock_write(&self, offset: u64, size: u64) -> Result<RwLockWriteGuard<'_, ()>> {
+		if !self.is_host_visible() {
+			return Err(Error::NotHostVisible);
+		}
+		let lock = self.map_lock.write().unwrap();
+		self.invalidate(offset, size)?;
+		Ok(lock)
+	}
+
+	/// The start of the mapping, as a pointer to `U`.
+	fn mapped_ptr<U>(&self) -> *mut U {
+		// the allocation is only aligned to the buffer's memory requirements, which needn't cover `U`
+		debug_assert!((self.mapped.0 as usize).is_multiple_of(align_of::<U>()), "mapping is misaligned for the type");
+		self.mapped.0 as *mut U
+	}
 }
 impl<T> Buffer<[T]> {
-	pub fn init_slice<CPU: Bit>(
+	/// Host-visible buffers are persistently mapped.
+	pub fn init_slice<L: MemoryLocation>(
 		device: Arc<Device>,
 		len: usize,
-		_cpu: CPU,
+		_location: L,
 		usage: BufferUsageFlags,
-	) -> BufferInit<[T], CPU> {
+	) -> Result<BufferInit<[T], L>> {
 		let size = size_of::<T>() as u64 * len as u64;
+		Ok(BufferInit::new(Self::new(device, size, usage, L::MEMORY_USAGE)?))
+	}
 
-		let ci = ash::vk::BufferCreateInfo::builder().size(size).usage(usage).build();
+	pub fn len(&self) -> u64 {
+		self.size / size_of::<T>() as u64
+	}
 
-		let usage = if CPU::BOOL { MemoryUsage::CpuOnly } else { MemoryUsage::GpuOnly };
-		let aci = AllocationCreateInfo { usage, ..Default::default() };
+	/// Panics if `range` is out of bounds.
+	pub fn slice(self: &Arc<Self>, range: impl RangeBounds<u64>) -> BufferSlice<T> {
+		BufferSlice::from(self.clone()).slice(range)
+	}
+}
+impl<T: Pod> Buffer<[T]> {
+	/// Fails with `Error::NotHostVisible` unless the buffer is host-visible. Blocks while a `map_mut` guard is held.
+	pub fn map(&self) -> Result<BufferMap<'_, [T]>> {
+		self.map_range(..)
+	}
 
-		let (vk, alloc, _) = device.allocator.create_buffer(&ci, &aci).unwrap();
+	/// Like `map`, but flushes non-coherent memory when the guard is dropped.
+	pub fn map_mut(&self) -> Result<BufferMapMut<'_, [T]>> {
+		self.map_range_mut(..)
+	}
 
-		let buf = Arc::new(Self { device, vk, alloc, size, phantom: PhantomData });
-		BufferInit::new(buf)
+	/// Like `map`, but only invalidates the elements in `range`. Panics if `range` is out of bounds.
+	pub fn map_range(&self, range: impl RangeBounds<u64>) -> Result<BufferMap<'_, [T]>> {
+		let range = resolve_range(range, self.len());
+		let (offset, size) = (range.start * size_of::<T>() as u64, (range.end - range.start) * size_of::<T>() as u64);
+		let lock = self.lock_read(offset, size)?;
+		let data = unsafe {
+			slice::from_raw_parts(self.mapped_ptr::<T>().add(range.start as _), (range.end - range.start) as _)
+		};
+		Ok(BufferMap { _lock: lock, data })
 	}
 
-	pub fn len(&self) -> u64 {
-		self.size / size_of::<T>() as u64
+	/// Like `map_mut`, but only invalidates and flushes the elements in `range`. Panics if `range` is out of bounds.
+	pub fn map_range_mut(&self, range: impl RangeBounds<u64>) -> Result<BufferMapMut<'_, [T]>> {
+		let range = resolve_range(range, self.len());
+		let (offset, size) = (range.start * size_of::<T>() as u64, (range.end - range.start) * size_of::<T>() as u64);
+		let lock = self.lock_write(offset, size)?;
+		let data = unsafe {
+			slice::from_raw_parts_mut(self.mapped_ptr::<T>().add(range.start as _), (range.end - range.start) as _)
+		};
+		Ok(BufferMapMut { buf: self, _lock: lock, data, offset, size, dirty: false })
+	}
+}
+impl<T: Pod> Buffer<[T]> {
+	/// Copies the buffer into a new `GpuToCpu` buffer on `queue` and blocks until it can be read back. The buffer needs
+	/// `TRANSFER_SRC` usage, and any writes to it must already have been submitted to `queue`.
+	pub fn download(self: &Arc<Self>, queue: &Arc<Queue>, pool: &Arc<CommandPool>) -> Result<Vec<T>> {
+		let readback = self.download_buffer(queue, pool)?;
+		let data = readback.map()?.to_vec();
+		Ok(data)
+	}
+}
+impl<T> Buffer<T> {
+	pub fn init<L: MemoryLocation>(
+		device: Arc<Device>,
+		_location: L,
+		usage: BufferUsageFlags,
+	) -> Result<BufferInit<T, L>> {
+		Ok(BufferInit::new(Self::new(device, size_of::<T>() as _, usage, L::MEMORY_USAGE)?))
+	}
+}
+impl<T: Pod> Buffer<T> {
+	pub fn from_data<L: HostVisible>(
+		device: Arc<Device>,
+		location: L,
+		usage: BufferUsageFlags,
+		data: &T,
+	) -> Result<Arc<Buffer<T>>> {
+		Self::init(device, location, usage)?.copy_from(data)
+	}
+
+	/// Fails with `Error::NotHostVisible` unless the buffer is host-visible. Blocks while a `map_mut` guard is held.
+	pub fn map(&self) -> Result<BufferMap<'_, T>> {
+		let lock = self.lock_read(0, self.size)?;
+		let data = unsafe { &*self.mapped_ptr::<T>() };
+		Ok(BufferMap { _lock: lock, data })
+	}
+
+	/// Like `map`, but flushes non-coherent memory when the guard is dropped.
+	pub fn map_mut(&self) -> Result<BufferMapMut<'_, T>> {
+		let lock = self.lock_write(0, self.size)?;
+		let data = unsafe { &mut *self.mapped_ptr::<T>() };
+		Ok(BufferMapMut { buf: self, _lock: lock, data, offset: 0, size: self.size, dirty: false })
+	}
+}
+impl<T: Pod> Buffer<T> {
+	/// Copies the buffer into a new `GpuToCpu` buffer on `queue` and blocks until it can be read back. The buffer needs
+	/// `TRANSFER_SRC` usage, and any writes to it must already have been submitted to `queue`.
+	pub fn download(self: &Arc<Self>, queue: &Arc<Queue>, pool: &Arc<CommandPool>) -> Result<T> {
+		let readback = self.download_buffer(queue, pool)?;
+		let data = *readback.map()?;
+		Ok(data)
 	}
 }
 impl<T: ?Sized> Drop for Buffer<T> {
@@ -56,42 +293,360 @@ impl<T: ?Sized> BufferAbstract for Buffer<T> {
 	}
 }
 
-pub struct BufferInit<T: ?Sized, CPU> {
+/// The GPU address of a buffer, for passing to shaders without a descriptor. Keeps the buffer alive, and so does any
+/// command buffer it is recorded into.
+pub struct DeviceAddress<T: ?Sized> {
+	buffer: Arc<Buffer<T>>,
+	address: u64,
+}
+impl<T: ?Sized> DeviceAddress<T> {
+	pub fn buffer(&self) -> &Arc<Buffer<T>> {
+		&self.buffer
+	}
+
+	pub fn get(&self) -> u64 {
+		self.address
+	}
+}
+impl<T: ?Sized> Clone for DeviceAddress<T> {
+	fn clone(&self) -> Self {
+		Self { buffer: self.buffer.clone(), address: self.address }
+	}
+}
+
+/// A range of elements in a buffer, which keeps the buffer alive.
+pub struct BufferSlice<T> {
+	buffer: Arc<Buffer<[T]>>,
+	range: Range<u64>,
+}
+impl<T> BufferSlice<T> {
+	pub fn buffer(&self) -> &Arc<Buffer<[T]>> {
+		&self.buffer
+	}
+
+	pub fn len(&self) -> u64 {
+		self.range.end - self.range.start
+	}
+
+	/// Offset into the buffer in bytes.
+	pub fn offset(&self) -> u64 {
+		self.range.start * size_of::<T>() as u64
+	}
+
+	/// Size in bytes.
+	pub fn size(&self) -> u64 {
+		self.len() * size_of::<T>() as u64
+	}
+
+	/// `range` is relative to this slice. Panics if it is out of bounds.
+	pub fn slice(&self, range: impl RangeBounds<u64>) -> BufferSlice<T> {
+		let Range { start, end } = resolve_range(range, self.len());
+		let range = self.range.start + start..self.range.start + end;
+		BufferSlice { buffer: self.buffer.clone(), range }
+	}
+}
+impl<T> Clone for BufferSlice<T> {
+	fn clone(&self) -> Self {
+		Self { buffer: self.buffer.clone(), range: self.range.clone() }
+	}
+}
+impl<T> From<Arc<Buffer<[T]>>> for BufferSlice<T> {
+	fn from(buffer: Arc<Buffer<[T]>>) -> Self {
+		let range = 0..buffer.len();
+		Self { buffer, range }
+	}
+}
+
+/// A typed view of a buffer, for `UNIFORM_TEXEL_BUFFER` and `STORAGE_TEXEL_BUFFER` descriptors.
+pub struct BufferView {
+	buffer: Arc<dyn BufferAbstract + Send + Sync>,
+	device: Arc<Device>,
+	pub vk: vk::BufferView,
+}
+impl BufferView {
+	/// Fails with:
+	/// - `Error::MissingUsage` unless the buffer has `UNIFORM_TEXEL_BUFFER` or `STORAGE_TEXEL_BUFFER` usage.
+	/// - `Error::Misaligned` if the offset isn't a multiple of `minTexelBufferOffsetAlignment`, or the size isn't a
+	///   multiple of the texel size.
+	/// - `Error::UnsupportedFormat` if `format` can't be used for every texel buffer usage the buffer was created with.
+	/// - `Error::LimitExceeded` if the slice holds more than `maxTexelBufferElements` texels.
+	pub fn new<T: Send + Sync + 'static>(buffer: impl Into<BufferSlice<T>>, format: Format) -> Result<Arc<Self>> {
+		let slice = buffer.into();
+		let buffer = slice.buffer();
+		let physical_device = buffer.device.physical_device();
+		let limits = *physical_device.properties().limits();
+
+		let texel_usage = BufferUsageFlags::UNIFORM_TEXEL_BUFFER | BufferUsageFlags::STORAGE_TEXEL_BUFFER;
+		if !buffer.usage.intersects(texel_usage) {
+			return Err(Error::MissingUsage("UNIFORM_TEXEL_BUFFER or STORAGE_TEXEL_BUFFER"));
+		}
+		let alignment = limits.min_texel_buffer_offset_alignment.max(1);
+		if !slice.offset().is_multiple_of(alignment) {
+			return Err(Error::Misaligned { what: "texel buffer offset", value: slice.offset(), alignment });
+		}
+
+		let mut required = FormatFeatureFlags::empty();
+		if buffer.usage.contains(BufferUsageFlags::UNIFORM_TEXEL_BUFFER) {
+			required |= FormatFeatureFlags::UNIFORM_TEXEL_BUFFER;
+		}
+		if buffer.usage.contains(BufferUsageFlags::STORAGE_TEXEL_BUFFER) {
+			required |= FormatFeatureFlags::STORAGE_TEXEL_BUFFER;
+		}
+		if !physical_device.get_format_properties(format).buffer_features.contains(required) {
+			return Err(Error::UnsupportedFormat(format));
+		}
+
+		let texel_size = match texel_block(format, ImageAspectFlags::COLOR) {
+			Some((size, 1, 1)) => size,
+			_ => return Err(Error::UnsupportedFormat(format)),
+		};
+		if !slice.size().is_multiple_of(texel_size) {
+			return Err(Error::Misaligned { what: "texel buffer size", value: slice.size(), alignment: texel_size });
+		}
+		let (texels, max) = (slice.size() / texel_size, limits.max_texel_buffer_elements as u64);
+		if texels > max {
+			return Err(Error::LimitExceeded { limit: "maxTexelBufferElements", value: texels, max });
+		}
+
+		let ci = vk::BufferViewCreateInfo::builder()
+			.buffer(buffer.vk)
+			.format(format)
+			.offset(slice.offset())
+			.range(slice.size());
+		buffer.device.check_lost()?;
+		let vk = buffer.device.check(unsafe { buffer.device.vk.create_buffer_view(&ci, None) })?;
+		Ok(Arc::new(Self { buffer: buffer.clone(), device: buffer.device.clone(), vk }))
+	}
+
+	pub fn buffer(&self) -> &Arc<dyn BufferAbstract + Send + Sync> {
+		&self.buffer
+	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
+	}
+}
+impl Drop for BufferView {
+	fn drop(&mut self) {
+		unsafe { self.device.vk.destroy_buffer_view(self.vk, None) };
+	}
+}
+
+pub struct BufferInit<T: ?Sized, L> {
 	buf: Arc<Buffer<T>>,
-	phantom: PhantomData<CPU>,
+	phantom: PhantomData<L>,
 }
-impl<T: ?Sized, CPU> BufferInit<T, CPU> {
+impl<T: ?Sized, L> BufferInit<T, L> {
 	fn new(buf: Arc<Buffer<T>>) -> Self {
 		Self { buf, phantom: PhantomData }
 	}
 }
-impl<T: Send + Sync + 'static, CPU> BufferInit<[T], CPU> {
+impl<T: ?Sized + Send + Sync + 'static, L> BufferInit<T, L> {
 	pub fn copy_from_buffer(
 		self,
 		queue: &Arc<Queue>,
 		pool: &Arc<CommandPool>,
-		buffer: Arc<Buffer<[T]>>,
-	) -> (Arc<Buffer<[T]>>, SubmitFuture) {
-		let cmd = pool.record(true, false).copy_buffer(buffer, self.buf.clone()).build();
+		buffer: Arc<Buffer<T>>,
+	) -> Result<(Arc<Buffer<T>>, SubmitFuture)> {
+		let cmd = pool.record(true, false)?.copy_buffer(buffer, self.buf.clone()).build()?;
 		let future = queue.submit(cmd);
-		(self.buf, future)
+		Ok((self.buf, future))
+	}
+}
+impl<T: Pod, L: HostVisible> BufferInit<T, L> {
+	pub fn copy_from(self, data: &T) -> Result<Arc<Buffer<T>>> {
+		let mut map = self.buf.map_mut()?;
+		*map = *data;
+		map.unmap()?;
+		Ok(self.buf)
+	}
+}
+impl<T: Pod, L: HostVisible> BufferInit<[T], L> {
+	pub fn copy_from_slice(self, data: &[T]) -> Result<Arc<Buffer<[T]>>> {
+		let mut map = self.buf.map_mut()?;
+		map.copy_from_slice(data);
+		map.unmap()?;
+		Ok(self.buf)
+	}
+}
+
+/// Types that can be safely copied to and from device memory as raw bytes: every bit pattern is valid, and there is no
+/// padding or pointers. Use `pod!` to declare structs that implement it.
+pub unsafe trait Pod: Copy + Send + Sync + 'static {}
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+unsafe impl Pod for vk::DispatchIndirectCommand {}
+unsafe impl Pod for vk::DrawIndexedIndirectCommand {}
+unsafe impl Pod for vk::DrawIndirectCommand {}
+
+// statically sized nalgebra matrices are `#[repr(C)]` arrays of their scalar type
+macro_rules! nalgebra_pod {
+	($($rows:ident)*, $cols:tt) => {
+		nalgebra_pod!(@cols [$($rows)*] $cols);
+	};
+	(@cols $rows:tt [$($cols:ident)*]) => {
+		$(nalgebra_pod!(@rows $rows $cols);)*
+	};
+	(@rows [$($rows:ident)*] $cols:ident) => {
+		$(unsafe impl<N: Pod + Scalar> Pod for Matrix<N, $rows, $cols, ArrayStorage<N, $rows, $cols>> {})*
+	};
+}
+nalgebra_pod!(U1 U2 U3 U4, [U1 U2 U3 U4]);
+unsafe impl<N: Pod + Scalar> Pod for Point<N, U1> {}
+unsafe impl<N: Pod + Scalar> Pod for Point<N, U2> {}
+unsafe impl<N: Pod + Scalar> Pod for Point<N, U3> {}
+unsafe impl<N: Pod + Scalar> Pod for Point<N, U4> {}
+
+/// Declares a `#[repr(C)]` struct that implements `Pod`. Fails to compile if a field isn't `Pod` or the struct would
+/// contain padding.
+///
+/// Only non-generic structs with named fields are accepted. Implement `Pod` by hand for tuple or generic structs.
+#[macro_export]
+macro_rules! pod {
+	($(#[$attr:meta])* $vis:vis struct $name:ident { $($field_vis:vis $field:ident: $ty:ty),* $(,)? }) => {
+		$(#[$attr])*
+		#[derive(Clone, Copy)]
+		#[repr(C)]
+		$vis struct $name {
+			$($field_vis $field: $ty),*
+		}
+		const _: () = {
+			fn assert_pod<T: $crate::buffer::Pod>() {}
+			fn assert_fields() {
+				$(assert_pod::<$ty>();)*
+			}
+			assert!(::std::mem::size_of::<$name>() == 0 $(+ ::std::mem::size_of::<$ty>())*, "struct contains padding");
+		};
+		unsafe impl $crate::buffer::Pod for $name {}
+	};
+}
+
+/// Where a buffer's memory is allocated. Passed to `Buffer::init_slice` as a value, like `GpuOnly`.
+pub trait MemoryLocation {
+	const MEMORY_USAGE: MemoryUsage;
+}
+
+/// Memory locations that can be mapped, and so initialized from and read by the host.
+pub trait HostVisible: MemoryLocation {}
+
+/// Device-local memory, for resources only the device reads and writes.
+pub struct GpuOnly;
+impl MemoryLocation for GpuOnly {
+	const MEMORY_USAGE: MemoryUsage = MemoryUsage::GpuOnly;
+}
+
+/// Host memory, for staging buffers.
+pub struct CpuOnly;
+impl MemoryLocation for CpuOnly {
+	const MEMORY_USAGE: MemoryUsage = MemoryUsage::CpuOnly;
+}
+impl HostVisible for CpuOnly {}
+
+/// Memory written by the host and read by the device, such as per-frame uniforms.
+pub struct CpuToGpu;
+impl MemoryLocation for CpuToGpu {
+	const MEMORY_USAGE: MemoryUsage = MemoryUsage::CpuToGpu;
+}
+impl HostVisible for CpuToGpu {}
+
+/// Memory written by the device and read back by the host.
+pub struct GpuToCpu;
+impl MemoryLocation for GpuToCpu {
+	const MEMORY_USAGE: MemoryUsage = MemoryUsage::GpuToCpu;
+}
+impl HostVisible for GpuToCpu {}
+
+pub struct BufferMap<'a, T: ?Sized> {
+	_lock: RwLockReadGuard<'a, ()>,
+	data: &'a T,
+}
+impl<T: ?Sized> Deref for BufferMap<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		self.data
 	}
 }
-impl<T: Copy + 'static> BufferInit<[T], B1> {
-	pub fn copy_from_slice(self, data: &[T]) -> Arc<Buffer<[T]>> {
-		let buf = self.buf;
-		let allocator = &buf.device.allocator;
-		let alloc = &buf.alloc;
 
-		let bufdata = allocator.map_memory(&alloc).unwrap();
-		let bufdata = unsafe { slice::from_raw_parts_mut(bufdata as *mut T, (buf.size / size_of::<T>() as u64) as _) };
-		bufdata.copy_from_slice(data);
-		allocator.unmap_memory(&alloc).unwrap();
+/// Flushes the mapped range of non-coherent memory when dropped, if it was written to. Use `flush` or `unmap` to
+/// handle errors, which `Drop` can only log.
+pub struct BufferMapMut<'a, T: ?Sized> {
+	buf: &'a Buffer<T>,
+	_lock: RwLockWriteGuard<'a, ()>,
+	data: &'a mut T,
+	// the mapped range in bytes
+	offset: u64,
+	size: u64,
+	dirty: bool,
+}
+impl<T: ?Sized> BufferMapMut<'_, T> {
+	/// Makes writes so far visible to the device.
+	pub fn flush(&mut self) -> Result<()> {
+		if self.dirty {
+			self.buf.flush(self.offset, self.size)?;
+			self.dirty = false;
+		}
+		Ok(())
+	}
 
-		buf
+	/// Flushes and releases the mapping.
+	pub fn unmap(mut self) -> Result<()> {
+		self.flush()
 	}
 }
+impl<T: ?Sized> Deref for BufferMapMut<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		self.data
+	}
+}
+impl<T: ?Sized> DerefMut for BufferMapMut<'_, T> {
+	fn deref_mut(&mut self) -> &mut T {
+		self.dirty = true;
+		self.data
+	}
+}
+impl<T: ?Sized> Drop for BufferMapMut<'_, T> {
+	fn drop(&mut self) {
+		if let Err(err) = self.flush() {
+			error!("failed to flush mapped buffer: {}", err);
+		}
+	}
+}
+
+struct MappedPtr(*mut u8);
+// only dereferenced while holding Buffer::map_lock
+unsafe impl Send for MappedPtr {}
+unsafe impl Sync for MappedPtr {}
 
 pub trait BufferAbstract {
 	fn vk(&self) -> vk::Buffer;
 }
+
+/// Resolves `range` against `0..len`, panicking if it is out of bounds.
+fn resolve_range(range: impl RangeBounds<u64>, len: u64) -> Range<u64> {
+	let start = match range.start_bound() {
+		Bound::Included(&start) => Some(start),
+		Bound::Excluded(&start) => start.checked_add(1),
+		Bound::Unbounded => Some(0),
+	};
+	let end = match range.end_bound() {
+		Bound::Included(&end) => end.checked_add(1),
+		Bound::Excluded(&end) => Some(end),
+		Bound::Unbounded => Some(len),
+	};
+	match (start, end) {
+		(Some(start), Some(end)) if start <= end && end <= len => start..end,
+		_ => panic!("range out of bounds"),
+	}
+}
@@ -1,12 +1,18 @@
-pub use ash::vk::{BufferMemoryBarrier, ClearValue, MemoryBarrier, PipelineStageFlags};
+pub use ash::vk::{
+	AccessFlags, BufferCopy, BufferImageCopy, BufferMemoryBarrier, ClearValue, DispatchIndirectCommand, ImageBlit,
+	ImageCopy, ImageResolve, MemoryBarrier, PipelineStageFlags,
+};
 
 use crate::{
-	buffer::{Buffer, BufferAbstract},
+	buffer::{Buffer, BufferAbstract, BufferSlice, DeviceAddress, Pod},
 	descriptor::DescriptorSet,
 	device::Device,
-	image::{ClearColorValue, Framebuffer, Image, ImageLayout},
-	physical_device::QueueFamily,
-	pipeline::{GraphicsPipeline, PipelineLayout},
+	error::Result,
+	image::{
+		texel_block, ClearColorValue, Filter, Framebuffer, Image, ImageLayout, ImageSubresourceLayers, SampleCountFlags,
+	},
+	physical_device::{FormatFeatureFlags, QueueFamily},
+	pipeline::{ComputePipeline, GraphicsPipeline, PipelineLayout},
 	render_pass::RenderPass,
 	shader::ShaderStageFlags,
 	sync::Resource,
@@ -17,8 +23,9 @@ use nalgebra::Vector3;
 use std::{
 	cell::{RefCell, RefMut},
 	collections::HashMap,
+	ffi::CString,
 	marker::PhantomData,
-	mem::size_of,
+	mem::{size_of, size_of_val},
 	slice,
 	sync::{Arc, Mutex},
 };
@@ -37,11 +44,13 @@ impl CommandPool {
 		Arc::new(Self { device, queue_family, transient, pools: ThreadLocal::new(), free: Mutex::default() })
 	}
 
-	pub fn record(self: &Arc<CommandPool>, one_time: bool, simultaneous: bool) -> CommandBufferBuilder<B0> {
-		let cmd = self.get_cmdbuf(false);
+	/// Fails with `Error::DeviceLost` once the device is lost.
+	pub fn record(self: &Arc<CommandPool>, one_time: bool, simultaneous: bool) -> Result<CommandBufferBuilder<B0>> {
+		self.device.check_lost()?;
+		let cmd = self.get_cmdbuf(false)?;
 		unsafe {
-			self.begin(cmd, one_time, simultaneous, &None);
-			CommandBufferBuilder::new(self.clone(), self.get_pool().vk, one_time, simultaneous, None, cmd)
+			self.begin(cmd, one_time, simultaneous, &None)?;
+			Ok(CommandBufferBuilder::new(self.clone(), self.get_pool()?.vk, one_time, simultaneous, None, cmd))
 		}
 	}
 
@@ -50,19 +59,20 @@ impl CommandPool {
 		one_time: bool,
 		simultaneous: bool,
 		inherit: Option<InheritanceInfo>,
-	) -> CommandBufferBuilder<B1> {
-		let cmd = self.get_cmdbuf(true);
+	) -> Result<CommandBufferBuilder<B1>> {
+		self.device.check_lost()?;
+		let cmd = self.get_cmdbuf(true)?;
 		unsafe {
-			self.begin(cmd, one_time, simultaneous, &inherit);
-			CommandBufferBuilder::new(self.clone(), self.get_pool().vk, one_time, simultaneous, inherit, cmd)
+			self.begin(cmd, one_time, simultaneous, &inherit)?;
+			Ok(CommandBufferBuilder::new(self.clone(), self.get_pool()?.vk, one_time, simultaneous, inherit, cmd))
 		}
 	}
 
 	/// Resets the thread-local pool.
 	///
 	/// Panics if any command buffers have been built from the thread-local pool but not dropped.
-	pub fn reset(&self, release: bool) {
-		let mut pool = self.get_pool();
+	pub fn reset(&self, release: bool) -> Result<()> {
+		let mut pool = self.get_pool()?;
 
 		let (free_primary, free_secondary) = {
 			let free_lock = self.free.lock().unwrap();
@@ -74,12 +84,14 @@ impl CommandPool {
 
 		let flags =
 			if release { vk::CommandPoolResetFlags::RELEASE_RESOURCES } else { vk::CommandPoolResetFlags::empty() };
-		unsafe { self.device.vk.reset_command_pool(pool.vk, flags) }.unwrap();
+		self.device.check(unsafe { self.device.vk.reset_command_pool(pool.vk, flags) })?;
 
 		let mut free_lock = self.free.lock().unwrap();
 		let free = free_lock.get_mut(&pool.vk).unwrap();
-		pool.cmds.primary.extend(free.primary.drain(..));
-		pool.cmds.secondary.extend(free.secondary.drain(..));
+		pool.cmds.primary.append(&mut free.primary);
+		pool.cmds.secondary.append(&mut free.secondary);
+
+		Ok(())
 	}
 
 	unsafe fn begin(
@@ -88,7 +100,7 @@ impl CommandPool {
 		one_time: bool,
 		simultaneous: bool,
 		inherit: &Option<InheritanceInfo>,
-	) {
+	) -> Result<()> {
 		let mut flags = vk::CommandBufferUsageFlags::empty();
 		if one_time {
 			flags |= vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT;
@@ -106,11 +118,11 @@ impl CommandPool {
 		}
 
 		let bi = vk::CommandBufferBeginInfo::builder().flags(flags).inheritance_info(&inheritance_info);
-		self.device.vk.begin_command_buffer(cmd, &bi).unwrap();
+		self.device.check(self.device.vk.begin_command_buffer(cmd, &bi))
 	}
 
-	fn get_cmdbuf(&self, secondary: bool) -> vk::CommandBuffer {
-		let mut pool = self.get_pool();
+	fn get_cmdbuf(&self, secondary: bool) -> Result<vk::CommandBuffer> {
+		let mut pool = self.get_pool()?;
 
 		let cmdslen = if secondary { pool.cmds.secondary.len() } else { pool.cmds.primary.len() };
 		if cmdslen == 0 {
@@ -125,7 +137,7 @@ impl CommandPool {
 
 			let ci =
 				vk::CommandBufferAllocateInfo::builder().command_pool(pool.vk).level(level).command_buffer_count(size);
-			let cmds = unsafe { self.device.vk.allocate_command_buffers(&ci) }.unwrap();
+			let cmds = self.device.check(unsafe { self.device.vk.allocate_command_buffers(&ci) })?;
 
 			if secondary {
 				pool.cmds.secondary = cmds;
@@ -136,25 +148,24 @@ impl CommandPool {
 			}
 		}
 
-		if secondary { pool.cmds.secondary.pop() } else { pool.cmds.primary.pop() }.unwrap()
+		Ok(if secondary { pool.cmds.secondary.pop() } else { pool.cmds.primary.pop() }.unwrap())
 	}
 
-	fn get_pool(&self) -> RefMut<CommandPoolInner> {
-		self.pools
-			.get_or(|| {
-				let mut flags = vk::CommandPoolCreateFlags::empty();
-				if self.transient {
-					flags |= vk::CommandPoolCreateFlags::TRANSIENT;
-				};
+	fn get_pool(&self) -> Result<RefMut<'_, CommandPoolInner>> {
+		let pool = self.pools.get_or_try(|| -> Result<_> {
+			let mut flags = vk::CommandPoolCreateFlags::empty();
+			if self.transient {
+				flags |= vk::CommandPoolCreateFlags::TRANSIENT;
+			};
 
-				let ci = vk::CommandPoolCreateInfo::builder().flags(flags).queue_family_index(self.queue_family.idx);
-				let vk = unsafe { self.device.vk.create_command_pool(&ci, None) }.unwrap();
+			let ci = vk::CommandPoolCreateInfo::builder().flags(flags).queue_family_index(self.queue_family.idx);
+			let vk = self.device.check(unsafe { self.device.vk.create_command_pool(&ci, None) })?;
 
-				self.free.lock().unwrap().insert(vk, CmdCollection::new());
+			self.free.lock().unwrap().insert(vk, CmdCollection::new());
 
-				RefCell::new(CommandPoolInner { vk, cmds: CmdCollection::new(), primary_size: 0, secondary_size: 0 })
-			})
-			.borrow_mut()
+			Ok(RefCell::new(CommandPoolInner { vk, cmds: CmdCollection::new(), primary_size: 0, secondary_size: 0 }))
+		})?;
+		Ok(pool.borrow_mut())
 	}
 }
 impl Drop for CommandPool {
@@ -213,6 +224,17 @@ impl<SEC: Bit> CommandBufferBuilder<SEC> {
 		}
 	}
 
+	/// Opens a label region shown in debuggers. Does nothing unless `VK_EXT_debug_utils` is enabled. `name` is
+	/// handled as in `Device::set_object_name`.
+	pub fn begin_label(self, name: &str, color: [f32; 4]) -> Result<Self> {
+		if let Some(ext_debug_utils) = &self.pool.device.instance().ext_debug_utils {
+			let name = CString::new(name)?;
+			let label = vk::DebugUtilsLabelEXT::builder().label_name(&name).color(color);
+			unsafe { ext_debug_utils.cmd_begin_debug_utils_label(self.vk, &label) };
+		}
+		Ok(self)
+	}
+
 	pub fn begin_render_pass(
 		mut self,
 		render_pass: Arc<RenderPass>,
@@ -234,47 +256,55 @@ impl<SEC: Bit> CommandBufferBuilder<SEC> {
 		self
 	}
 
-	pub fn build(self) -> Arc<CommandBuffer<SEC>> {
+	pub fn build(self) -> Result<Arc<CommandBuffer<SEC>>> {
 		unsafe {
-			self.pool.device.vk.end_command_buffer(self.vk).unwrap();
-			Arc::new(CommandBuffer {
+			self.pool.device.check(self.pool.device.vk.end_command_buffer(self.vk))?;
+			Ok(Arc::new(CommandBuffer {
 				pool: self.pool,
 				vkpool: self.vkpool,
 				vk: self.vk,
 				_resources: self.resources,
 				sec: PhantomData,
-			})
+			}))
 		}
 	}
 
-	pub fn bind_descriptor_sets(
-		mut self,
+	pub fn bind_compute_descriptor_sets(
+		self,
 		layout: Arc<PipelineLayout>,
 		first_set: u32,
 		descriptor_sets: impl IntoIterator<Item = Arc<DescriptorSet>>,
 		dynamic_offsets: &[u32],
 	) -> Self {
-		let descriptor_sets = descriptor_sets.into_iter();
-		let (lower, upper) = descriptor_sets.size_hint();
-		let mut descriptor_set_vks = Vec::with_capacity(upper.unwrap_or(lower));
-		for set in descriptor_sets {
-			descriptor_set_vks.push(set.vk);
-			self.resources.push(Resource::DescriptorSet(set));
-		}
+		let bind_point = vk::PipelineBindPoint::COMPUTE;
+		self.bind_descriptor_sets_at(bind_point, layout, first_set, descriptor_sets, dynamic_offsets)
+	}
 
-		unsafe {
-			self.pool.device.vk.cmd_bind_descriptor_sets(
-				self.vk,
-				vk::PipelineBindPoint::GRAPHICS,
-				layout.vk,
-				first_set,
-				&descriptor_set_vks,
-				dynamic_offsets,
-			)
-		};
+	pub fn bind_compute_pipeline(mut self, pipeline: Arc<ComputePipeline>) -> Self {
+		unsafe { self.pool.device.vk.cmd_bind_pipeline(self.vk, vk::PipelineBindPoint::COMPUTE, pipeline.vk) };
+		self.resources.push(Resource::ComputePipeline(pipeline));
+		self
+	}
 
-		self.resources.push(Resource::PipelineLayout(layout));
+	pub fn bind_descriptor_sets(
+		self,
+		layout: Arc<PipelineLayout>,
+		first_set: u32,
+		descriptor_sets: impl IntoIterator<Item = Arc<DescriptorSet>>,
+		dynamic_offsets: &[u32],
+	) -> Self {
+		let bind_point = vk::PipelineBindPoint::GRAPHICS;
+		self.bind_descriptor_sets_at(bind_point, layout, first_set, descriptor_sets, dynamic_offsets)
+	}
 
+	pub fn bind_index_buffer<T: IndexType + Send + Sync + 'static>(
+		mut self,
+		indices: impl Into<BufferSlice<T>>,
+	) -> Self {
+		let indices = indices.into();
+		let (buffer, offset) = (indices.buffer().vk, indices.offset());
+		unsafe { self.pool.device.vk.cmd_bind_index_buffer(self.vk, buffer, offset, T::INDEX_TYPE) };
+		self.resources.push(Resource::Buffer(indices.buffer().clone()));
 		self
 	}
 
@@ -284,6 +314,18 @@ impl<SEC: Bit> CommandBufferBuilder<SEC> {
 		self
 	}
 
+	pub fn bind_vertex_buffer<T: Send + Sync + 'static>(
+		mut self,
+		binding: u32,
+		vertices: impl Into<BufferSlice<T>>,
+	) -> Self {
+		let vertices = vertices.into();
+		let (buffer, offset) = (vertices.buffer().vk, vertices.offset());
+		unsafe { self.pool.device.vk.cmd_bind_vertex_buffers(self.vk, binding, &[buffer], &[offset]) };
+		self.resources.push(Resource::Buffer(vertices.buffer().clone()));
+		self
+	}
+
 	pub fn bind_vertex_buffers(
 		mut self,
 		first_binding: u32,
@@ -302,11 +344,46 @@ impl<SEC: Bit> CommandBufferBuilder<SEC> {
 		self
 	}
 
+	/// Blits from `src` in `TRANSFER_SRC_OPTIMAL` to `dst` in `TRANSFER_DST_OPTIMAL`, recording nothing if `regions` is
+	/// empty. Panics if either image is multisampled, if the formats don't support `BLIT_SRC` and `BLIT_DST`
+	/// respectively, or if a region is out of bounds of either image.
+	pub fn blit_image(mut self, src: Arc<Image>, dst: Arc<Image>, regions: &[ImageBlit], filter: Filter) -> Self {
+		if regions.is_empty() {
+			return self;
+		}
+		assert!(src.samples() == SampleCountFlags::TYPE_1 && dst.samples() == SampleCountFlags::TYPE_1);
+		let physical_device = self.pool.device.physical_device();
+		let src_features = physical_device.get_format_properties(src.format()).optimal_tiling_features;
+		let dst_features = physical_device.get_format_properties(dst.format()).optimal_tiling_features;
+		assert!(src_features.contains(FormatFeatureFlags::BLIT_SRC));
+		assert!(dst_features.contains(FormatFeatureFlags::BLIT_DST));
+		for region in regions {
+			check_blit_region(&src, &region.src_subresource, &region.src_offsets);
+			check_blit_region(&dst, &region.dst_subresource, &region.dst_offsets);
+		}
+
+		unsafe {
+			self.pool.device.vk.cmd_blit_image(
+				self.vk,
+				src.vk,
+				vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+				dst.vk,
+				vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+				regions,
+				filter,
+			)
+		};
+
+		self.resources.push(Resource::Image(src));
+		self.resources.push(Resource::Image(dst));
+		self
+	}
+
 	pub fn clear_color_image(self, image: Arc<Image>, color: ClearColorValue) -> Self {
 		let image_subresource = vk::ImageSubresourceRange::builder()
 			.aspect_mask(vk::ImageAspectFlags::COLOR)
-			.level_count(1)
-			.layer_count(1)
+			.level_count(vk::REMAINING_MIP_LEVELS)
+			.layer_count(vk::REMAINING_ARRAY_LAYERS)
 			.build();
 
 		unsafe {
@@ -322,61 +399,197 @@ impl<SEC: Bit> CommandBufferBuilder<SEC> {
 		self
 	}
 
-	pub fn copy_buffer<T: Send + Sync + ?Sized + 'static>(mut self, src: Arc<Buffer<T>>, dst: Arc<Buffer<T>>) -> Self {
-		assert!(src.size() <= dst.size());
+	/// Panics if `dst` is smaller than `src`, or is the same buffer.
+	pub fn copy_buffer<T: Send + Sync + ?Sized + 'static>(self, src: Arc<Buffer<T>>, dst: Arc<Buffer<T>>) -> Self {
+		assert!(src.size() <= dst.size() && src.vk != dst.vk);
+
+		let region = vk::BufferCopy::builder().size(src.size()).build();
+		self.copy_buffer_region(src, dst, region)
+	}
+
+	/// Records nothing if `regions` is empty. Panics if a region is empty or out of bounds of either buffer, or if
+	/// `src` and `dst` are the same buffer and a region's source overlaps any region's destination.
+	pub fn copy_buffer_regions<T, U>(mut self, src: Arc<Buffer<T>>, dst: Arc<Buffer<U>>, regions: &[BufferCopy]) -> Self
+	where
+		T: Send + Sync + ?Sized + 'static,
+		U: Send + Sync + ?Sized + 'static,
+	{
+		if regions.is_empty() {
+			return self;
+		}
+		for region in regions {
+			assert!(region.size > 0);
+			assert!(in_bounds(region.src_offset, region.size, src.size()), "source region out of bounds");
+			assert!(in_bounds(region.dst_offset, region.size, dst.size()), "destination region out of bounds");
+		}
+		if src.vk == dst.vk {
+			for a in regions {
+				for b in regions {
+					let overlap = a.src_offset < b.dst_offset + b.size && b.dst_offset < a.src_offset + a.size;
+					assert!(!overlap, "source and destination regions overlap");
+				}
+			}
+		}
 
-		let regions = [vk::BufferCopy::builder().size(src.size()).build()];
-		unsafe { self.pool.device.vk.cmd_copy_buffer(self.vk, src.vk, dst.vk, &regions) };
+		unsafe { self.pool.device.vk.cmd_copy_buffer(self.vk, src.vk, dst.vk, regions) };
 
 		self.resources.push(Resource::Buffer(src));
 		self.resources.push(Resource::Buffer(dst));
 		self
 	}
 
+	/// Panics if `src` is empty, if `dst` is shorter than `src`, or if they overlap.
+	pub fn copy_buffer_slice<T: Send + Sync + 'static>(self, src: BufferSlice<T>, dst: BufferSlice<T>) -> Self {
+		assert!(src.size() > 0 && src.len() <= dst.len());
+		if src.buffer().vk == dst.buffer().vk {
+			let overlap = src.offset() < dst.offset() + src.size() && dst.offset() < src.offset() + src.size();
+			assert!(!overlap, "source and destination slices overlap");
+		}
+
+		let region =
+			vk::BufferCopy::builder().src_offset(src.offset()).dst_offset(dst.offset()).size(src.size()).build();
+		self.copy_buffer_region(src.buffer().clone(), dst.buffer().clone(), region)
+	}
+
+	/// Copies tightly packed texels to mip level 0 and layer 0 of `dst`, which must be in `TRANSFER_DST_OPTIMAL`.
+	/// Panics if the texels would be read from past the end of `src`, or if `image_extent` doesn't fit in `dst`.
 	pub fn copy_buffer_to_image<T: Send + Sync + 'static>(
-		mut self,
-		src: Arc<Buffer<[T]>>,
+		self,
+		src: impl Into<BufferSlice<T>>,
 		dst: Arc<Image>,
 		image_extent: &Vector3<u32>,
 	) -> Self {
-		assert!(src.len() <= dst.len());
+		let src = src.into();
+		let region = full_region(src.offset(), image_extent);
+		check_buffer_image_region(src.offset() + src.size(), &dst, &region);
+
+		self.copy_buffer_to_image_unchecked(src.buffer().clone(), dst, &[region])
+	}
+
+	/// Copies to `dst` in `TRANSFER_DST_OPTIMAL`, recording nothing if `regions` is empty. Panics if a region is out of
+	/// bounds of `src` or `dst`, though regions of formats without a known texel size aren't checked against `src`.
+	pub fn copy_buffer_to_image_regions<T: Send + Sync + ?Sized + 'static>(
+		self,
+		src: Arc<Buffer<T>>,
+		dst: Arc<Image>,
+		regions: &[BufferImageCopy],
+	) -> Self {
+		if regions.is_empty() {
+			return self;
+		}
+		for region in regions {
+			check_buffer_image_region(src.size(), &dst, region);
+		}
+
+		self.copy_buffer_to_image_unchecked(src, dst, regions)
+	}
+
+	/// Copies from `src` in `TRANSFER_SRC_OPTIMAL` to `dst` in `TRANSFER_DST_OPTIMAL`, recording nothing if `regions`
+	/// is empty. Panics if a region is out of bounds of either image.
+	pub fn copy_image(mut self, src: Arc<Image>, dst: Arc<Image>, regions: &[ImageCopy]) -> Self {
+		if regions.is_empty() {
+			return self;
+		}
+		for region in regions {
+			check_image_region(&src, &region.src_subresource, region.src_offset, region.extent);
+			check_image_region(&dst, &region.dst_subresource, region.dst_offset, region.extent);
+		}
 
-		let image_subresource = vk::ImageSubresourceLayers::builder()
-			.aspect_mask(vk::ImageAspectFlags::COLOR)
-			.mip_level(0)
-			.base_array_layer(0)
-			.layer_count(1)
-			.build();
-		let image_extent =
-			vk::Extent3D::builder().width(image_extent.x).height(image_extent.y).depth(image_extent.z).build();
-		let regions = [vk::BufferImageCopy::builder()
-			.buffer_offset(0)
-			.buffer_row_length(0)
-			.buffer_image_height(0)
-			.image_subresource(image_subresource)
-			.image_offset(vk::Offset3D::default())
-			.image_extent(image_extent)
-			.build()];
 		unsafe {
-			self.pool.device.vk.cmd_copy_buffer_to_image(
+			self.pool.device.vk.cmd_copy_image(
 				self.vk,
 				src.vk,
+				vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
 				dst.vk,
 				vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-				&regions,
+				regions,
 			)
 		};
 
-		self.resources.push(Resource::Buffer(src));
+		self.resources.push(Resource::Image(src));
 		self.resources.push(Resource::Image(dst));
 		self
 	}
 
+	/// Copies mip level 0 and layer 0 of `src`, which must be in `TRANSFER_SRC_OPTIMAL`, as tightly packed texels.
+	/// Panics if the texels would be written past the end of `dst`.
+	pub fn copy_image_to_buffer<T: Send + Sync + 'static>(
+		self,
+		src: Arc<Image>,
+		dst: impl Into<BufferSlice<T>>,
+	) -> Self {
+		let dst = dst.into();
+		let region = full_region(dst.offset(), src.size());
+		check_buffer_image_region(dst.offset() + dst.size(), &src, &region);
+
+		self.copy_image_to_buffer_unchecked(src, dst.buffer().clone(), &[region])
+	}
+
+	/// Copies from `src` in `TRANSFER_SRC_OPTIMAL`, recording nothing if `regions` is empty. Panics if a region is out
+	/// of bounds of `src` or `dst`, though regions of formats without a known texel size aren't checked against `dst`.
+	pub fn copy_image_to_buffer_regions<T: Send + Sync + ?Sized + 'static>(
+		self,
+		src: Arc<Image>,
+		dst: Arc<Buffer<T>>,
+		regions: &[BufferImageCopy],
+	) -> Self {
+		if regions.is_empty() {
+			return self;
+		}
+		for region in regions {
+			check_buffer_image_region(dst.size(), &src, region);
+		}
+
+		self.copy_image_to_buffer_unchecked(src, dst, regions)
+	}
+
+	pub fn dispatch(self, group_count_x: u32, group_count_y: u32, group_count_z: u32) -> Self {
+		unsafe { self.pool.device.vk.cmd_dispatch(self.vk, group_count_x, group_count_y, group_count_z) };
+		self
+	}
+
+	/// Dispatches with the group counts in the first element of `args`.
+	pub fn dispatch_indirect(mut self, args: impl Into<BufferSlice<DispatchIndirectCommand>>) -> Self {
+		let args = args.into();
+		assert!(args.len() > 0);
+		unsafe { self.pool.device.vk.cmd_dispatch_indirect(self.vk, args.buffer().vk, args.offset()) };
+		self.resources.push(Resource::Buffer(args.buffer().clone()));
+		self
+	}
+
 	pub fn draw(self, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32) -> Self {
 		unsafe { self.pool.device.vk.cmd_draw(self.vk, vertex_count, instance_count, first_vertex, first_instance) };
 		self
 	}
 
+	pub fn draw_indexed(
+		self,
+		index_count: u32,
+		instance_count: u32,
+		first_index: u32,
+		vertex_offset: i32,
+		first_instance: u32,
+	) -> Self {
+		unsafe {
+			self.pool.device.vk.cmd_draw_indexed(
+				self.vk,
+				index_count,
+				instance_count,
+				first_index,
+				vertex_offset,
+				first_instance,
+			)
+		};
+		self
+	}
+
+	pub fn end_label(self) -> Self {
+		if let Some(ext_debug_utils) = &self.pool.device.instance().ext_debug_utils {
+			unsafe { ext_debug_utils.cmd_end_debug_utils_label(self.vk) };
+		}
+		self
+	}
+
 	pub fn end_render_pass(self) -> Self {
 		unsafe { self.pool.device.vk.cmd_end_render_pass(self.vk) };
 		self
@@ -395,6 +608,56 @@ impl<SEC: Bit> CommandBufferBuilder<SEC> {
 		self
 	}
 
+	/// Fills the buffer with copies of `data`. A size that isn't a multiple of 4 is rounded down.
+	pub fn fill_buffer<T: Send + Sync + ?Sized + 'static>(self, dst: Arc<Buffer<T>>, data: u32) -> Self {
+		self.fill_buffer_region(dst, 0, vk::WHOLE_SIZE, data)
+	}
+
+	/// Panics if the slice is empty, or unless its offset and size are multiples of 4.
+	pub fn fill_buffer_slice<T: Send + Sync + 'static>(self, dst: BufferSlice<T>, data: u32) -> Self {
+		assert!(dst.offset().is_multiple_of(4) && dst.size() > 0 && dst.size().is_multiple_of(4));
+
+		self.fill_buffer_region(dst.buffer().clone(), dst.offset(), dst.size(), data)
+	}
+
+	/// `name` is handled as in `Device::set_object_name`.
+	pub fn insert_label(self, name: &str, color: [f32; 4]) -> Result<Self> {
+		if let Some(ext_debug_utils) = &self.pool.device.instance().ext_debug_utils {
+			let name = CString::new(name)?;
+			let label = vk::DebugUtilsLabelEXT::builder().label_name(&name).color(color);
+			unsafe { ext_debug_utils.cmd_insert_debug_utils_label(self.vk, &label) };
+		}
+		Ok(self)
+	}
+
+	/// Records the commands added by `f` inside a label region.
+	pub fn label(self, name: &str, color: [f32; 4], f: impl FnOnce(Self) -> Self) -> Result<Self> {
+		Ok(f(self.begin_label(name, color)?).end_label())
+	}
+
+	/// A global memory barrier, covering every resource.
+	pub fn memory_barrier(
+		self,
+		src_stage_mask: PipelineStageFlags,
+		dst_stage_mask: PipelineStageFlags,
+		src_access_mask: AccessFlags,
+		dst_access_mask: AccessFlags,
+	) -> Self {
+		let barrier = vk::MemoryBarrier::builder().src_access_mask(src_access_mask).dst_access_mask(dst_access_mask);
+		unsafe {
+			self.pool.device.vk.cmd_pipeline_barrier(
+				self.vk,
+				src_stage_mask,
+				dst_stage_mask,
+				vk::DependencyFlags::empty(),
+				&[*barrier],
+				&[],
+				&[],
+			)
+		};
+		self
+	}
+
 	pub fn pipeline_barrier(
 		mut self,
 		src_stage_mask: PipelineStageFlags,
@@ -410,20 +673,13 @@ impl<SEC: Bit> CommandBufferBuilder<SEC> {
 
 			bar.img.set_layout(new_layout);
 
-			let (src_access_mask, dst_access_mask) = match (old_layout, new_layout) {
-				(ImageLayout::UNDEFINED, ImageLayout::TRANSFER_DST_OPTIMAL) => {
-					(vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE)
-				},
-				(ImageLayout::TRANSFER_DST_OPTIMAL, ImageLayout::SHADER_READ_ONLY_OPTIMAL) => {
-					(vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ)
-				},
-				_ => unimplemented!(),
-			};
+			let src_access_mask = layout_access(old_layout);
+			let dst_access_mask = layout_access(new_layout);
 
 			let subresource_range = vk::ImageSubresourceRange::builder()
 				.aspect_mask(vk::ImageAspectFlags::COLOR)
-				.level_count(1)
-				.layer_count(1)
+				.level_count(vk::REMAINING_MIP_LEVELS)
+				.layer_count(vk::REMAINING_ARRAY_LAYERS)
 				.build();
 			let barrier = vk::ImageMemoryBarrier::builder()
 				.src_access_mask(src_access_mask)
@@ -452,14 +708,14 @@ impl<SEC: Bit> CommandBufferBuilder<SEC> {
 		self
 	}
 
-	pub fn push_constants<T>(
+	pub fn push_constants<T: Pod>(
 		mut self,
 		layout: Arc<PipelineLayout>,
 		stage_flags: ShaderStageFlags,
 		offset: u32,
 		val: &T,
 	) -> Self {
-		assert!(size_of::<T>() % 4 == 0);
+		assert!(size_of::<T>().is_multiple_of(4));
 		assert!(stage_flags != ShaderStageFlags::empty());
 
 		let constants = unsafe { slice::from_raw_parts(val as *const _ as *const _, size_of::<T>()) };
@@ -467,6 +723,319 @@ impl<SEC: Bit> CommandBufferBuilder<SEC> {
 		self.resources.push(Resource::PipelineLayout(layout));
 		self
 	}
+
+	/// Pushes the address as a `u64` at `offset`.
+	pub fn push_device_address<T: Send + Sync + ?Sized + 'static>(
+		self,
+		layout: Arc<PipelineLayout>,
+		stage_flags: ShaderStageFlags,
+		offset: u32,
+		address: &DeviceAddress<T>,
+	) -> Self {
+		self.push_constants(layout, stage_flags, offset, &address.get()).use_device_address(address)
+	}
+
+	/// Resolves multisampled `src` in `TRANSFER_SRC_OPTIMAL` to single-sampled `dst` in `TRANSFER_DST_OPTIMAL`,
+	/// recording nothing if `regions` is empty. Panics if the formats differ or if a region is out of bounds of either
+	/// image.
+	pub fn resolve_image(mut self, src: Arc<Image>, dst: Arc<Image>, regions: &[ImageResolve]) -> Self {
+		if regions.is_empty() {
+			return self;
+		}
+		assert!(src.samples() != SampleCountFlags::TYPE_1 && dst.samples() == SampleCountFlags::TYPE_1);
+		assert!(src.format() == dst.format());
+		for region in regions {
+			check_image_region(&src, &region.src_subresource, region.src_offset, region.extent);
+			check_image_region(&dst, &region.dst_subresource, region.dst_offset, region.extent);
+		}
+
+		unsafe {
+			self.pool.device.vk.cmd_resolve_image(
+				self.vk,
+				src.vk,
+				vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+				dst.vk,
+				vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+				regions,
+			)
+		};
+
+		self.resources.push(Resource::Image(src));
+		self.resources.push(Resource::Image(dst));
+		self
+	}
+
+	/// Writes `data` inline in the command buffer, so it must be small. Panics unless the size of `T` is a multiple
+	/// of 4 and at most 65536 bytes.
+	pub fn update_buffer<T: Pod>(self, dst: Arc<Buffer<T>>, data: &T) -> Self {
+		self.update_buffer_region(dst, 0, slice::from_ref(data))
+	}
+
+	/// Writes `data` to the start of `dst` inline in the command buffer. Panics if `data` is longer than `dst`, or
+	/// unless the slice's offset and the size of `data` are multiples of 4 and the size is at most 65536 bytes.
+	pub fn update_buffer_slice<T: Pod>(self, dst: BufferSlice<T>, data: &[T]) -> Self {
+		assert!(data.len() as u64 <= dst.len());
+
+		self.update_buffer_region(dst.buffer().clone(), dst.offset(), data)
+	}
+
+	/// Keeps the buffer behind `address` alive as long as the command buffer, for addresses reaching shaders some other
+	/// way than `push_device_address`, such as inside another buffer.
+	pub fn use_device_address<T: Send + Sync + ?Sized + 'static>(mut self, address: &DeviceAddress<T>) -> Self {
+		self.resources.push(Resource::Buffer(address.buffer().clone()));
+		self
+	}
+
+	/// `pipeline_barrier` for barriers the layout table doesn't cover, such as queue family ownership transfers.
+	pub(crate) fn barrier(
+		mut self,
+		src_stage_mask: PipelineStageFlags,
+		dst_stage_mask: PipelineStageFlags,
+		buffer_memory_barriers: &[vk::BufferMemoryBarrier],
+		image_memory_barriers: &[vk::ImageMemoryBarrier],
+		resources: impl IntoIterator<Item = Resource>,
+	) -> Self {
+		unsafe {
+			self.pool.device.vk.cmd_pipeline_barrier(
+				self.vk,
+				src_stage_mask,
+				dst_stage_mask,
+				vk::DependencyFlags::empty(),
+				&[],
+				buffer_memory_barriers,
+				image_memory_barriers,
+			)
+		};
+		self.resources.extend(resources);
+		self
+	}
+
+	/// Untyped `copy_buffer`, which does no bounds checking.
+	pub(crate) fn copy_buffer_region(
+		mut self,
+		src: Arc<dyn BufferAbstract + Send + Sync>,
+		dst: Arc<dyn BufferAbstract + Send + Sync>,
+		region: vk::BufferCopy,
+	) -> Self {
+		unsafe { self.pool.device.vk.cmd_copy_buffer(self.vk, src.vk(), dst.vk(), &[region]) };
+
+		self.resources.push(Resource::Buffer(src));
+		self.resources.push(Resource::Buffer(dst));
+		self
+	}
+
+	fn bind_descriptor_sets_at(
+		mut self,
+		bind_point: vk::PipelineBindPoint,
+		layout: Arc<PipelineLayout>,
+		first_set: u32,
+		descriptor_sets: impl IntoIterator<Item = Arc<DescriptorSet>>,
+		dynamic_offsets: &[u32],
+	) -> Self {
+		let descriptor_sets = descriptor_sets.into_iter();
+		let (lower, upper) = descriptor_sets.size_hint();
+		let mut descriptor_set_vks = Vec::with_capacity(upper.unwrap_or(lower));
+		for set in descriptor_sets {
+			descriptor_set_vks.push(set.vk);
+			self.resources.push(Resource::DescriptorSet(set));
+		}
+
+		unsafe {
+			self.pool.device.vk.cmd_bind_descriptor_sets(
+				self.vk,
+				bind_point,
+				layout.vk,
+				first_set,
+				&descriptor_set_vks,
+				dynamic_offsets,
+			)
+		};
+
+		self.resources.push(Resource::PipelineLayout(layout));
+
+		self
+	}
+
+	fn copy_buffer_to_image_unchecked(
+		mut self,
+		src: Arc<dyn BufferAbstract + Send + Sync>,
+		dst: Arc<Image>,
+		regions: &[BufferImageCopy],
+	) -> Self {
+		unsafe {
+			self.pool.device.vk.cmd_copy_buffer_to_image(
+				self.vk,
+				src.vk(),
+				dst.vk,
+				vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+				regions,
+			)
+		};
+
+		self.resources.push(Resource::Buffer(src));
+		self.resources.push(Resource::Image(dst));
+		self
+	}
+
+	fn copy_image_to_buffer_unchecked(
+		mut self,
+		src: Arc<Image>,
+		dst: Arc<dyn BufferAbstract + Send + Sync>,
+		regions: &[BufferImageCopy],
+	) -> Self {
+		unsafe {
+			self.pool.device.vk.cmd_copy_image_to_buffer(
+				self.vk,
+				src.vk,
+				vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+				dst.vk(),
+				regions,
+			)
+		};
+
+		self.resources.push(Resource::Image(src));
+		self.resources.push(Resource::Buffer(dst));
+		self
+	}
+
+	fn fill_buffer_region(
+		mut self,
+		dst: Arc<dyn BufferAbstract + Send + Sync>,
+		offset: u64,
+		size: u64,
+		data: u32,
+	) -> Self {
+		unsafe { self.pool.device.vk.cmd_fill_buffer(self.vk, dst.vk(), offset, size, data) };
+
+		self.resources.push(Resource::Buffer(dst));
+		self
+	}
+
+	fn update_buffer_region<T: Pod>(
+		mut self,
+		dst: Arc<dyn BufferAbstract + Send + Sync>,
+		offset: u64,
+		data: &[T],
+	) -> Self {
+		let size = size_of_val(data);
+		assert!(offset.is_multiple_of(4));
+		assert!(size > 0 && size.is_multiple_of(4) && size <= 65536);
+
+		let bytes = unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, size) };
+		unsafe { self.pool.device.vk.cmd_update_buffer(self.vk, dst.vk(), offset, bytes) };
+
+		self.resources.push(Resource::Buffer(dst));
+		self
+	}
+}
+
+/// The accesses an image in `layout` is used for.
+pub(crate) fn layout_access(layout: ImageLayout) -> AccessFlags {
+	match layout {
+		ImageLayout::UNDEFINED => AccessFlags::empty(),
+		ImageLayout::PREINITIALIZED => AccessFlags::HOST_WRITE,
+		ImageLayout::COLOR_ATTACHMENT_OPTIMAL => {
+			AccessFlags::COLOR_ATTACHMENT_READ | AccessFlags::COLOR_ATTACHMENT_WRITE
+		},
+		ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+		| ImageLayout::DEPTH_ATTACHMENT_OPTIMAL
+		| ImageLayout::STENCIL_ATTACHMENT_OPTIMAL
+		| ImageLayout::DEPTH_READ_ONLY_STENCIL_ATTACHMENT_OPTIMAL
+		| ImageLayout::DEPTH_ATTACHMENT_STENCIL_READ_ONLY_OPTIMAL => {
+			AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+		},
+		ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL
+		| ImageLayout::DEPTH_READ_ONLY_OPTIMAL
+		| ImageLayout::STENCIL_READ_ONLY_OPTIMAL => {
+			AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | AccessFlags::SHADER_READ
+		},
+		ImageLayout::SHADER_READ_ONLY_OPTIMAL => AccessFlags::SHADER_READ,
+		ImageLayout::TRANSFER_SRC_OPTIMAL => AccessFlags::TRANSFER_READ,
+		ImageLayout::TRANSFER_DST_OPTIMAL => AccessFlags::TRANSFER_WRITE,
+		// presentation is ordered by semaphores, not access masks
+		ImageLayout::PRESENT_SRC_KHR => AccessFlags::empty(),
+		// GENERAL, and layouts from extensions
+		_ => AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE,
+	}
+}
+
+/// Panics if the region at `offset` of size `extent` in `subresource` is empty or not inside `img`.
+fn check_image_region(img: &Image, subresource: &ImageSubresourceLayers, offset: vk::Offset3D, extent: vk::Extent3D) {
+	check_subresource(img, subresource);
+
+	let size = img.mip_size(subresource.mip_level);
+	let fits =
+		|offset: i32, extent: u32, size: u32| offset >= 0 && extent > 0 && offset as u64 + extent as u64 <= size as u64;
+	assert!(fits(offset.x, extent.width, size.x));
+	assert!(fits(offset.y, extent.height, size.y));
+	assert!(fits(offset.z, extent.depth, size.z));
+}
+
+/// Panics if either corner of a blit region is outside `img`.
+fn check_blit_region(img: &Image, subresource: &ImageSubresourceLayers, offsets: &[vk::Offset3D; 2]) {
+	check_subresource(img, subresource);
+
+	let size = img.mip_size(subresource.mip_level);
+	for offset in offsets {
+		assert!((0..=size.x as i32).contains(&offset.x));
+		assert!((0..=size.y as i32).contains(&offset.y));
+		assert!((0..=size.z as i32).contains(&offset.z));
+	}
+}
+
+/// Panics if `subresource` has no layers, or names a mip level or layer `img` doesn't have.
+fn check_subresource(img: &Image, subresource: &ImageSubresourceLayers) {
+	assert!(subresource.mip_level < img.mip_levels());
+	assert!(subresource.layer_count > 0);
+	assert!(subresource.base_array_layer as u64 + subresource.layer_count as u64 <= img.array_layers() as u64);
+}
+
+/// `check_image_region`, and panics if the texels would be past `buffer_end` bytes into the buffer.
+fn check_buffer_image_region(buffer_end: u64, img: &Image, region: &BufferImageCopy) {
+	check_image_region(img, &region.image_subresource, region.image_offset, region.image_extent);
+
+	let block = texel_block(img.format(), region.image_subresource.aspect_mask);
+	let (block_size, block_width, block_height) = match block {
+		Some(block) => block,
+		None => return,
+	};
+	let extent = region.image_extent;
+	assert!(region.buffer_row_length == 0 || region.buffer_row_length >= extent.width);
+	assert!(region.buffer_image_height == 0 || region.buffer_image_height >= extent.height);
+	let row_length = if region.buffer_row_length == 0 { extent.width } else { region.buffer_row_length };
+	let image_height = if region.buffer_image_height == 0 { extent.height } else { region.buffer_image_height };
+	let row_size = row_length.div_ceil(block_width) as u64 * block_size;
+	let slice_size = image_height.div_ceil(block_height) as u64 * row_size;
+	// array layers are laid out like the slices of a 3D image
+	let slices = extent.depth as u64 * region.image_subresource.layer_count as u64;
+	let size = (slices - 1) * slice_size
+		+ (extent.height.div_ceil(block_height) as u64 - 1) * row_size
+		+ extent.width.div_ceil(block_width) as u64 * block_size;
+	assert!(in_bounds(region.buffer_offset, size, buffer_end), "buffer region out of bounds");
+}
+
+/// Whether `size` bytes at `offset` fit in `end` bytes, without overflowing.
+fn in_bounds(offset: u64, size: u64, end: u64) -> bool {
+	offset.checked_add(size).is_some_and(|region_end| region_end <= end)
+}
+
+/// A tightly packed copy of `extent` texels at mip level 0 and layer 0.
+fn full_region(buffer_offset: u64, extent: &Vector3<u32>) -> BufferImageCopy {
+	let image_subresource = vk::ImageSubresourceLayers::builder()
+		.aspect_mask(vk::ImageAspectFlags::COLOR)
+		.mip_level(0)
+		.base_array_layer(0)
+		.layer_count(1)
+		.build();
+	let image_extent = vk::Extent3D::builder().width(extent.x).height(extent.y).depth(extent.z).build();
+	vk::BufferImageCopy::builder()
+		.buffer_offset(buffer_offset)
+		.buffer_row_length(0)
+		.buffer_image_height(0)
+		.image_subresource(image_subresource)
+		.image_offset(vk::Offset3D::default())
+		.image_extent(image_extent)
+		.build()
 }
 
 pub struct ImageMemoryBarrier {
@@ -479,6 +1048,17 @@ impl ImageMemoryBarrier {
 	}
 }
 
+/// Index types accepted by `CommandBufferBuilder::bind_index_buffer`.
+pub trait IndexType {
+	const INDEX_TYPE: vk::IndexType;
+}
+impl IndexType for u16 {
+	const INDEX_TYPE: vk::IndexType = vk::IndexType::UINT16;
+}
+impl IndexType for u32 {
+	const INDEX_TYPE: vk::IndexType = vk::IndexType::UINT32;
+}
+
 pub struct InheritanceInfo {
 	pub render_pass: Arc<RenderPass>,
 	pub subpass: u32,
@@ -492,6 +1072,11 @@ pub struct CommandBuffer<SEC: Bit> {
 	_resources: Vec<Resource>,
 	sec: PhantomData<SEC>,
 }
+impl<SEC: Bit> CommandBuffer<SEC> {
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.pool.device.set_object_name(self.vk, name)
+	}
+}
 impl<SEC: Bit> Drop for CommandBuffer<SEC> {
 	fn drop(&mut self) {
 		let mut free_lock = self.pool.free.lock().unwrap();
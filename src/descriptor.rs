@@ -1,7 +1,9 @@
 pub use ash::vk::DescriptorType;
 
 use crate::{
+	buffer::{BufferSlice, BufferView},
 	device::Device,
+	error::Result,
 	image::{ImageLayout, ImageView, Sampler},
 	shader::ShaderStageFlags,
 	sync::Resource,
@@ -23,6 +25,10 @@ impl DescriptorSetLayout {
 	pub fn builder(device: Arc<Device>) -> DescriptorSetLayoutBuilder {
 		DescriptorSetLayoutBuilder::new(device)
 	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
+	}
 }
 impl Drop for DescriptorSetLayout {
 	fn drop(&mut self) {
@@ -40,15 +46,16 @@ impl DescriptorSetLayoutBuilder {
 		Self { device, bindings: vec![], immutable_samplers: vec![] }
 	}
 
-	pub fn build(self) -> Arc<DescriptorSetLayout> {
+	pub fn build(self) -> Result<Arc<DescriptorSetLayout>> {
 		let ci = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&self.bindings);
-		let vk = unsafe { self.device.vk.create_descriptor_set_layout(&ci, None) }.unwrap();
-		Arc::new(DescriptorSetLayout {
+		self.device.check_lost()?;
+		let vk = self.device.check(unsafe { self.device.vk.create_descriptor_set_layout(&ci, None) })?;
+		Ok(Arc::new(DescriptorSetLayout {
 			device: self.device,
 			vk,
 			_immutable_samplers: self.immutable_samplers,
 			binding_count: self.bindings.len() as _,
-		})
+		}))
 	}
 
 	pub fn desc(
@@ -93,12 +100,13 @@ pub struct DescriptorPool {
 	vk: vk::DescriptorPool,
 }
 impl DescriptorPool {
-	pub fn new(device: Arc<Device>, max_sets: u32, pool_sizes: Vec<DescriptorPoolSize>) -> Arc<Self> {
+	pub fn new(device: Arc<Device>, max_sets: u32, pool_sizes: Vec<DescriptorPoolSize>) -> Result<Arc<Self>> {
 		let ci = vk::DescriptorPoolCreateInfo::builder()
 			.max_sets(max_sets)
-			.pool_sizes(unsafe { transmute(&pool_sizes[..]) });
-		let vk = unsafe { device.vk.create_descriptor_pool(&ci, None) }.unwrap();
-		Arc::new(Self { device, vk })
+			.pool_sizes(unsafe { transmute::<&[DescriptorPoolSize], &[vk::DescriptorPoolSize]>(&pool_sizes[..]) });
+		device.check_lost()?;
+		let vk = device.check(unsafe { device.vk.create_descriptor_pool(&ci, None) })?;
+		Ok(Arc::new(Self { device, vk }))
 	}
 }
 impl Drop for DescriptorPool {
@@ -117,22 +125,27 @@ impl DescriptorSet {
 	pub fn alloc(
 		descriptor_pool: Arc<DescriptorPool>,
 		set_layouts: Vec<Arc<DescriptorSetLayout>>,
-	) -> impl Iterator<Item = Arc<DescriptorSet>> {
+	) -> Result<impl Iterator<Item = Arc<DescriptorSet>>> {
 		let set_layout_vks: Vec<_> = set_layouts.iter().map(|x| x.vk).collect();
 		let ci =
 			vk::DescriptorSetAllocateInfo::builder().descriptor_pool(descriptor_pool.vk).set_layouts(&set_layout_vks);
-		let vks = unsafe { descriptor_pool.device.vk.allocate_descriptor_sets(&ci) }.unwrap();
+		descriptor_pool.device.check_lost()?;
+		let vks = descriptor_pool.device.check(unsafe { descriptor_pool.device.vk.allocate_descriptor_sets(&ci) })?;
 
-		vks.into_iter().zip(set_layouts).map(move |(vk, layout)| {
+		Ok(vks.into_iter().zip(set_layouts).map(move |(vk, layout)| {
 			Arc::new(DescriptorSet {
 				_descriptor_pool: descriptor_pool.clone(),
 				vk,
 				resources: Mutex::new(vec![vec![]; layout.binding_count as _]),
 			})
-		})
+		}))
 	}
 
-	pub fn update_builder(device: &Device) -> DescriptorSetUpdate {
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self._descriptor_pool.device.set_object_name(self.vk, name)
+	}
+
+	pub fn update_builder(device: &Device) -> DescriptorSetUpdate<'_, 'static> {
 		DescriptorSetUpdate::new(device)
 	}
 }
@@ -140,14 +153,30 @@ impl DescriptorSet {
 pub struct DescriptorSetUpdate<'a, 'b> {
 	device: &'a Device,
 	writes: Vec<vk::WriteDescriptorSet>,
+	// pointed to by writes
+	image_infos: Vec<Vec<vk::DescriptorImageInfo>>,
+	buffer_infos: Vec<Vec<vk::DescriptorBufferInfo>>,
+	texel_buffer_views: Vec<Vec<vk::BufferView>>,
 	phantom: PhantomData<&'b u8>,
 }
 impl<'a> DescriptorSetUpdate<'a, 'static> {
 	fn new(device: &'a Device) -> Self {
-		Self { device, writes: vec![], phantom: PhantomData }
+		Self {
+			device,
+			writes: vec![],
+			image_infos: vec![],
+			buffer_infos: vec![],
+			texel_buffer_views: vec![],
+			phantom: PhantomData,
+		}
 	}
 }
 impl<'a, 'b> DescriptorSetUpdate<'a, 'b> {
+	pub fn submit(self) {
+		unsafe { self.device.vk.update_descriptor_sets(&self.writes, &[]) };
+	}
+
+	/// Writes nothing if `image_infos` is empty.
 	pub fn write<'c>(
 		mut self,
 		dst_set: &'b DescriptorSet,
@@ -155,7 +184,10 @@ impl<'a, 'b> DescriptorSetUpdate<'a, 'b> {
 		descriptor_type: DescriptorType,
 		image_infos: impl IntoIterator<Item = (Option<Arc<Sampler>>, Arc<ImageView>, ImageLayout)>,
 	) -> DescriptorSetUpdate<'a, 'c> {
-		let image_infos = image_infos.into_iter();
+		let mut image_infos = image_infos.into_iter().peekable();
+		if image_infos.peek().is_none() {
+			return self.rebind();
+		}
 		let (lower, upper) = image_infos.size_hint();
 		let size = upper.unwrap_or(lower);
 
@@ -188,11 +220,117 @@ impl<'a, 'b> DescriptorSetUpdate<'a, 'b> {
 			.image_info(&image_info_vks)
 			.build();
 		self.writes.push(write);
+		self.image_infos.push(image_info_vks);
 
-		DescriptorSetUpdate { device: self.device, writes: self.writes, phantom: PhantomData }
+		self.rebind()
 	}
 
-	pub fn submit(self) {
-		unsafe { self.device.vk.update_descriptor_sets(&self.writes, &[]) };
+	/// Writes nothing if `buffers` is empty. Panics if a slice's offset is not a multiple of
+	/// `minUniformBufferOffsetAlignment` or `minStorageBufferOffsetAlignment`, for uniform and storage buffers
+	/// respectively.
+	pub fn write_buffers<'c, T: Send + Sync + 'static>(
+		mut self,
+		dst_set: &'b DescriptorSet,
+		dst_binding: u32,
+		descriptor_type: DescriptorType,
+		buffers: impl IntoIterator<Item = BufferSlice<T>>,
+	) -> DescriptorSetUpdate<'a, 'c> {
+		let mut buffers = buffers.into_iter().peekable();
+		if buffers.peek().is_none() {
+			return self.rebind();
+		}
+		let (lower, upper) = buffers.size_hint();
+		let size = upper.unwrap_or(lower);
+
+		let mut resources = dst_set.resources.lock().unwrap();
+		let resources = &mut resources[dst_binding as usize];
+		resources.clear();
+		resources.reserve(size);
+
+		let mut buffer_info_vks = Vec::with_capacity(size);
+
+		let limits = *self.device.physical_device().properties().limits();
+		let alignment = match descriptor_type {
+			DescriptorType::UNIFORM_BUFFER | DescriptorType::UNIFORM_BUFFER_DYNAMIC => {
+				limits.min_uniform_buffer_offset_alignment
+			},
+			DescriptorType::STORAGE_BUFFER | DescriptorType::STORAGE_BUFFER_DYNAMIC => {
+				limits.min_storage_buffer_offset_alignment
+			},
+			_ => 1,
+		};
+
+		for buffer in buffers {
+			assert!(buffer.offset().is_multiple_of(alignment.max(1)), "buffer offset is not aligned");
+			let info = vk::DescriptorBufferInfo::builder()
+				.buffer(buffer.buffer().vk)
+				.offset(buffer.offset())
+				.range(buffer.size())
+				.build();
+			buffer_info_vks.push(info);
+
+			resources.push(Resource::Buffer(buffer.buffer().clone()));
+		}
+
+		let write = vk::WriteDescriptorSet::builder()
+			.dst_set(dst_set.vk)
+			.dst_binding(dst_binding)
+			.descriptor_type(descriptor_type)
+			.buffer_info(&buffer_info_vks)
+			.build();
+		self.writes.push(write);
+		self.buffer_infos.push(buffer_info_vks);
+
+		self.rebind()
+	}
+
+	/// Writes nothing if `views` is empty.
+	pub fn write_buffer_views<'c>(
+		mut self,
+		dst_set: &'b DescriptorSet,
+		dst_binding: u32,
+		descriptor_type: DescriptorType,
+		views: impl IntoIterator<Item = Arc<BufferView>>,
+	) -> DescriptorSetUpdate<'a, 'c> {
+		let mut views = views.into_iter().peekable();
+		if views.peek().is_none() {
+			return self.rebind();
+		}
+		let (lower, upper) = views.size_hint();
+		let size = upper.unwrap_or(lower);
+
+		let mut resources = dst_set.resources.lock().unwrap();
+		let resources = &mut resources[dst_binding as usize];
+		resources.clear();
+		resources.reserve(size);
+
+		let mut view_vks = Vec::with_capacity(size);
+
+		for view in views {
+			view_vks.push(view.vk);
+			resources.push(Resource::BufferView(view));
+		}
+
+		let write = vk::WriteDescriptorSet::builder()
+			.dst_set(dst_set.vk)
+			.dst_binding(dst_binding)
+			.descriptor_type(descriptor_type)
+			.texel_buffer_view(&view_vks)
+			.build();
+		self.writes.push(write);
+		self.texel_buffer_views.push(view_vks);
+
+		self.rebind()
+	}
+
+	fn rebind<'c>(self) -> DescriptorSetUpdate<'a, 'c> {
+		DescriptorSetUpdate {
+			device: self.device,
+			writes: self.writes,
+			image_infos: self.image_infos,
+			buffer_infos: self.buffer_infos,
+			texel_buffer_views: self.texel_buffer_views,
+			phantom: PhantomData,
+		}
 	}
 }
@@ -3,9 +3,12 @@ pub use ash::vk::BufferUsageFlags;
 
 use crate::{
 	command::CommandBuffer,
-	physical_device::{PhysicalDevice, QueueFamily},
+	error::{Error, Result},
+	instance::{negotiate, Version},
+	physical_device::{Features, PhysicalDevice, QueueFamily, QueueFamilyIndices, SelectedPhysicalDevice},
 	pipeline::{GraphicsPipelineBuilder, PipelineLayout},
 	render_pass::RenderPass,
+	surface::Surface,
 	sync::{GpuFuture, SubmitState},
 };
 use ash::{
@@ -13,57 +16,54 @@ use ash::{
 	version::{DeviceV1_0, InstanceV1_0},
 	vk, Device as VkDevice,
 };
-use std::sync::Arc;
+use std::{
+	ffi::{CStr, CString},
+	marker::PhantomData,
+	mem, ptr, result,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
 use typenum::B0;
-use vk_mem::{Allocator, AllocatorCreateInfo};
+use vk_mem::{Allocator, AllocatorCreateFlags, AllocatorCreateInfo};
 
 pub struct Device {
 	physical_device: Arc<PhysicalDevice>,
 	pub vk: VkDevice,
 	pub khr_swapchain: khr::Swapchain,
+	pub khr_buffer_device_address: Option<vk::KhrBufferDeviceAddressFn>,
 	pub allocator: Allocator,
+	extensions: Vec<CString>,
+	features: Features,
+	// through either Vulkan 1.2 or VK_KHR_buffer_device_address
+	buffer_device_address: bool,
+	lost: AtomicBool,
 }
 impl Device {
-	// TODO: find a better way to request queues
-	pub fn new<'a>(
-		physical_device: Arc<PhysicalDevice>,
-		qfams: impl IntoIterator<Item = (QueueFamily, &'a [f32])>,
-	) -> (Arc<Self>, impl Iterator<Item = Arc<Queue>>) {
-		let qcis: Vec<_> = qfams
-			.into_iter()
-			.inspect(|(qfam, _)| assert!(qfam.physical_device() == &physical_device))
-			.map(|(qfam, priorities)| {
-				vk::DeviceQueueCreateInfo::builder().queue_family_index(qfam.idx).queue_priorities(priorities).build()
-			})
-			.collect();
-
-		let exts = [b"VK_KHR_swapchain\0".as_ptr() as _];
-
-		let ci = vk::DeviceCreateInfo::builder().queue_create_infos(&qcis).enabled_extension_names(&exts);
-		let vk = unsafe { physical_device.instance().vk.create_device(physical_device.vk, &ci, None) }.unwrap();
-
-		let khr_swapchain = khr::Swapchain::new(&physical_device.instance().vk, &vk);
-
-		let ci = AllocatorCreateInfo {
-			physical_device: physical_device.vk,
-			device: vk.clone(),
-			instance: physical_device.instance().vk.clone(),
-			..AllocatorCreateInfo::default()
-		};
-		let allocator = Allocator::new(&ci).unwrap();
-
-		let device = Arc::new(Self { physical_device, vk, khr_swapchain, allocator });
-
-		let device2 = device.clone();
-		let queues = qcis
-			.into_iter()
-			.map(move |qci| {
-				let device = device2.clone();
-				(0..qci.queue_count).map(move |idx| unsafe { device.get_queue(qci.queue_family_index, idx) })
-			})
-			.flatten();
+	/// Creates a device with the queues described by `request`. Roles that can't get a queue of their own share one.
+	pub fn new(physical_device: Arc<PhysicalDevice>, request: QueueRequest<'_>) -> Result<(Arc<Self>, Queues)> {
+		Self::builder(physical_device).queues(request).build()
+	}
 
-		(device, queues)
+	/// Starts with `VK_KHR_swapchain` as an optional extension if the instance has `VK_KHR_surface` enabled.
+	pub fn builder<'a>(physical_device: Arc<PhysicalDevice>) -> DeviceBuilder<'a> {
+		let mut extensions = vec![];
+		// headless instances have no surfaces to present to
+		if physical_device.instance().is_extension_enabled(khr::Surface::name()) {
+			extensions.push((khr::Swapchain::name().to_owned(), false));
+		}
+
+		DeviceBuilder {
+			physical_device,
+			queues: QueueRequest::new(),
+			families: None,
+			extensions,
+			features: Features::default(),
+			optional_features: Features::default(),
+			next: ptr::null_mut(),
+			_next: PhantomData,
+		}
 	}
 
 	pub fn build_compute_pipeline(self: &Arc<Self>, layout: Arc<PipelineLayout>) -> ComputePipelineBuilder {
@@ -78,14 +78,78 @@ impl Device {
 		GraphicsPipelineBuilder::new(self.clone(), layout, render_pass)
 	}
 
+	pub fn enabled_extensions(&self) -> impl Iterator<Item = &CStr> {
+		self.extensions.iter().map(|x| x.as_c_str())
+	}
+
+	/// Features from `Features` that were enabled, not including those enabled through `DeviceBuilder::push_next`.
+	pub fn enabled_features(&self) -> &Features {
+		&self.features
+	}
+
 	pub fn instance(&self) -> &Arc<Instance> {
 		self.physical_device.instance()
 	}
 
+	/// Whether the `bufferDeviceAddress` feature was enabled. Vulkan 1.2 devices must enable it through
+	/// `Features::vulkan12`. Older devices need `VK_KHR_buffer_device_address`, with its feature struct chained
+	/// through `DeviceBuilder::push_next`, and `VK_KHR_device_group` on Vulkan 1.0, which `build` adds itself.
+	pub fn is_buffer_device_address_enabled(&self) -> bool {
+		self.buffer_device_address
+	}
+
+	pub fn is_extension_enabled(&self, name: &CStr) -> bool {
+		self.enabled_extensions().any(|x| x == name)
+	}
+
+	/// Whether any call has returned `VK_ERROR_DEVICE_LOST`. A lost device can't be recovered, only recreated.
+	pub fn is_lost(&self) -> bool {
+		self.lost.load(Ordering::Relaxed)
+	}
+
 	pub fn physical_device(&self) -> &Arc<PhysicalDevice> {
 		&self.physical_device
 	}
 
+	/// Names `handle` in validation messages and debuggers. Does nothing unless `VK_EXT_debug_utils` is enabled.
+	///
+	/// Fails with `Error::NulByte` if `name` contains a NUL byte.
+	pub fn set_object_name<H: vk::Handle>(&self, handle: H, name: &str) -> Result<()> {
+		let ext_debug_utils = match &self.instance().ext_debug_utils {
+			Some(ext_debug_utils) => ext_debug_utils,
+			None => return Ok(()),
+		};
+
+		let name = CString::new(name)?;
+		let info = vk::DebugUtilsObjectNameInfoEXT::builder()
+			.object_type(H::TYPE)
+			.object_handle(handle.as_raw())
+			.object_name(&name);
+		self.check(unsafe { ext_debug_utils.debug_utils_set_object_name(self.vk.handle(), &info) })
+	}
+
+	/// Blocks until all queues are idle.
+	pub fn wait_idle(&self) -> Result<()> {
+		self.check_lost()?;
+		self.check(unsafe { self.vk.device_wait_idle() })
+	}
+
+	/// Converts a Vulkan or allocator result, marking the device as lost on `VK_ERROR_DEVICE_LOST`.
+	pub(crate) fn check<T, E: Into<Error>>(&self, res: result::Result<T, E>) -> Result<T> {
+		res.map_err(|err| {
+			let err = err.into();
+			if let Error::DeviceLost = err {
+				self.lost.store(true, Ordering::Relaxed);
+			}
+			err
+		})
+	}
+
+	/// Fails with `Error::DeviceLost` once the device is lost, for calls that would otherwise go ahead regardless.
+	pub(crate) fn check_lost(&self) -> Result<()> {
+		if self.is_lost() { Err(Error::DeviceLost) } else { Ok(()) }
+	}
+
 	pub(crate) unsafe fn get_queue(self: &Arc<Self>, queue_family_index: u32, queue_index: u32) -> Arc<Queue> {
 		let vk = self.vk.get_device_queue(queue_family_index, queue_index);
 
@@ -103,6 +167,326 @@ impl Drop for Device {
 	}
 }
 
+pub struct DeviceBuilder<'a> {
+	physical_device: Arc<PhysicalDevice>,
+	queues: QueueRequest<'a>,
+	// from `queue_families`, instead of searching in `build`
+	families: Option<QueueFamilyIndices>,
+	// (name, required)
+	extensions: Vec<(CString, bool)>,
+	features: Features,
+	optional_features: Features,
+	next: *mut vk::BaseOutStructure,
+	_next: PhantomData<&'a mut ()>,
+}
+impl<'a> DeviceBuilder<'a> {
+	pub fn build(self) -> Result<(Arc<Device>, Queues)> {
+		let physical_device = self.physical_device;
+		let request = self.queues;
+
+		let families: Vec<_> = physical_device.get_queue_family_properties().map(|props| props.queue_count()).collect();
+		let found = match self.families {
+			Some(found) => found,
+			None => physical_device.find_queue_families(request.present)?,
+		};
+
+		let role = if request.present.is_some() { "graphics and present" } else { "graphics" };
+		let graphics =
+			if request.graphics { Some(found.graphics.ok_or(Error::MissingQueueFamily(role))?) } else { None };
+		// the selector may have been given a different surface, or none
+		if let (Some(graphics), Some(surface), Some(_)) = (graphics, request.present, self.families) {
+			let khr_surface = &physical_device.instance().khr_surface;
+			if !unsafe { khr_surface.get_physical_device_surface_support(physical_device.vk, graphics, surface) }? {
+				return Err(Error::MissingQueueFamily(role));
+			}
+		}
+		let compute = if request.compute {
+			Some(found.compute.or(found.graphics).ok_or(Error::MissingQueueFamily("compute"))?)
+		} else {
+			None
+		};
+		let transfer = if request.transfer {
+			Some(found.transfer.or(found.compute).or(found.graphics).ok_or(Error::MissingQueueFamily("transfer"))?)
+		} else {
+			None
+		};
+
+		// (family, queue index) for each role, reusing the family's last queue once it runs out
+		let mut counts = vec![0; families.len()];
+		let mut assign = |family: Option<u32>| {
+			family.map(|family| {
+				let idx = counts[family as usize].min(families[family as usize] - 1);
+				counts[family as usize] += 1;
+				(family, idx)
+			})
+		};
+		let (graphics, compute, transfer) = (assign(graphics), assign(compute), assign(transfer));
+
+		let priorities = vec![1.0; counts.iter().copied().max().unwrap_or(0) as usize];
+		let qcis: Vec<_> = counts
+			.iter()
+			.enumerate()
+			.filter(|(_, &count)| count > 0)
+			.map(|(family, &count)| {
+				let count = count.min(families[family]) as usize;
+				vk::DeviceQueueCreateInfo::builder()
+					.queue_family_index(family as _)
+					.queue_priorities(&priorities[..count])
+					.build()
+			})
+			.collect();
+
+		let api_version = physical_device.api_version();
+		let mut extensions = self.extensions;
+		// VMA chains VkMemoryAllocateFlagsInfoKHR for device addresses, which is only core from Vulkan 1.1
+		let khr_bda = extensions.iter().find(|(name, _)| name.as_c_str() == vk::KhrBufferDeviceAddressFn::name());
+		if let (Some(&(_, required)), true) = (khr_bda, api_version < Version::new(1, 1, 0)) {
+			extensions.push((vk::KhrDeviceGroupFn::name().to_owned(), required));
+		}
+		let available = physical_device.get_extension_properties()?;
+		let available =
+			available.iter().map(|props| unsafe { CStr::from_ptr(props.extension_name.as_ptr()) }).collect();
+		let extensions = negotiate("extension", &available, extensions).map_err(Error::MissingExtensions)?;
+		let extension_ptrs: Vec<_> = extensions.iter().map(|x| x.as_ptr()).collect();
+
+		let supported = physical_device.features();
+		let missing = self.features.missing_from(&supported);
+		if !missing.is_empty() {
+			return Err(Error::MissingFeatures(missing));
+		}
+		let features = self.features.union(&self.optional_features).intersection(&supported);
+
+		let mut ci = vk::DeviceCreateInfo::builder().queue_create_infos(&qcis).enabled_extension_names(&extension_ptrs);
+		// the 1.1 and 1.2 feature structs can only be chained on 1.2 devices, and are all zero otherwise
+		let (mut vulkan11, mut vulkan12) = (*features.vulkan11(), *features.vulkan12());
+		let mut features2 = vk::PhysicalDeviceFeatures2 { features: *features.core(), ..Default::default() };
+		if api_version >= Version::new(1, 2, 0) {
+			if let Some(s_type) = unsafe { chained_promoted_features(self.next) } {
+				return Err(Error::PromotedFeatures(s_type));
+			}
+			vulkan12.p_next = self.next as _;
+			vulkan11.p_next = &mut vulkan12 as *mut _ as _;
+			features2.p_next = &mut vulkan11 as *mut _ as _;
+			ci = ci.push_next(&mut features2);
+		} else {
+			ci = ci.enabled_features(&features2.features);
+			ci.p_next = self.next as _;
+		}
+		let vk = unsafe { physical_device.instance().vk.create_device(physical_device.vk, &ci, None) }?;
+
+		let khr_swapchain = khr::Swapchain::new(&physical_device.instance().vk, &vk);
+		let khr_buffer_device_address =
+			if extensions.iter().any(|x| x.as_c_str() == vk::KhrBufferDeviceAddressFn::name()) {
+				Some(vk::KhrBufferDeviceAddressFn::load(|name| unsafe {
+					mem::transmute(physical_device.instance().vk.get_device_proc_addr(vk.handle(), name.as_ptr()))
+				}))
+			} else {
+				None
+			};
+		let device_group = api_version >= Version::new(1, 1, 0)
+			|| extensions.iter().any(|x| x.as_c_str() == vk::KhrDeviceGroupFn::name());
+		let buffer_device_address = features.vulkan12().buffer_device_address == vk::TRUE
+			|| khr_buffer_device_address.is_some()
+				&& device_group
+				&& unsafe { chained_buffer_device_address(self.next) };
+
+		// VMA_ALLOCATOR_CREATE_BUFFER_DEVICE_ADDRESS_BIT in the VMA 2.4.0-development bundled with vk-mem 0.2.2, which
+		// doesn't name it. vk-mem also leaves vulkanApiVersion at 1.0, so VMA uses the KHR names on every device.
+		let flags = if buffer_device_address {
+			unsafe { AllocatorCreateFlags::from_bits_unchecked(0x20) }
+		} else {
+			AllocatorCreateFlags::NONE
+		};
+		let ci = AllocatorCreateInfo {
+			physical_device: physical_device.vk,
+			device: vk.clone(),
+			instance: physical_device.instance().vk.clone(),
+			flags,
+			..AllocatorCreateInfo::default()
+		};
+		let allocator = Allocator::new(&ci).inspect_err(|_| unsafe { vk.destroy_device(None) })?;
+
+		let lost = AtomicBool::new(false);
+		let device = Arc::new(Device {
+			physical_device,
+			vk,
+			khr_swapchain,
+			khr_buffer_device_address,
+			allocator,
+			extensions,
+			features,
+			buffer_device_address,
+			lost,
+		});
+
+		let mut created: Vec<((u32, u32), Arc<Queue>)> = vec![];
+		let mut get_queue = |role: Option<(u32, u32)>| {
+			role.map(|key| match created.iter().find(|(x, _)| *x == key) {
+				Some((_, queue)) => queue.clone(),
+				None => {
+					let queue = unsafe { device.get_queue(key.0, key.1) };
+					created.push((key, queue.clone()));
+					queue
+				},
+			})
+		};
+		let queues =
+			Queues { graphics: get_queue(graphics), compute: get_queue(compute), transfer: get_queue(transfer) };
+
+		Ok((device, queues))
+	}
+
+	pub fn extension(mut self, name: &CStr) -> Self {
+		self.extensions.push((name.to_owned(), true));
+		self
+	}
+
+	/// Fails to build if any of `features` are unsupported.
+	pub fn features(mut self, features: Features) -> Self {
+		self.features = self.features.union(&features);
+		self
+	}
+
+	pub fn optional_extension(mut self, name: &CStr) -> Self {
+		self.extensions.push((name.to_owned(), false));
+		self
+	}
+
+	/// Enables whichever of `features` are supported.
+	pub fn optional_features(mut self, features: Features) -> Self {
+		self.optional_features = self.optional_features.union(&features);
+		self
+	}
+
+	/// Chains a struct that `Features` doesn't cover, such as an extension's feature struct. It is not validated,
+	/// except that `build` fails with `Error::PromotedFeatures` on Vulkan 1.2 devices if it is a feature struct that
+	/// `Features::vulkan11` or `Features::vulkan12` covers, since those are always chained there.
+	pub fn push_next<T: vk::ExtendsDeviceCreateInfo>(mut self, next: &'a mut T) -> Self {
+		let next = next as *mut T as *mut vk::BaseOutStructure;
+		unsafe {
+			// append any existing chain on `next` after it
+			let mut last = next;
+			while !(*last).p_next.is_null() {
+				last = (*last).p_next;
+			}
+			(*last).p_next = self.next;
+		}
+		self.next = next;
+		self
+	}
+
+	/// Uses the families picked by `PhysicalDeviceSelector` instead of searching again. Panics if `selected` is for a
+	/// different physical device.
+	pub fn queue_families(mut self, selected: &SelectedPhysicalDevice) -> Self {
+		assert!(selected.physical_device == self.physical_device);
+		let idx = |family: &QueueFamily| family.idx;
+		self.families = Some(QueueFamilyIndices {
+			graphics: Some(selected.graphics.idx),
+			compute: selected.compute.as_ref().map(idx),
+			transfer: selected.transfer.as_ref().map(idx),
+		});
+		self
+	}
+
+	pub fn queues(mut self, queues: QueueRequest<'a>) -> Self {
+		self.queues = queues;
+		self
+	}
+}
+
+/// The first struct in the `push_next` chain whose features were promoted to Vulkan 1.1 or 1.2, and which can't be
+/// chained alongside `PhysicalDeviceVulkan11Features` and `PhysicalDeviceVulkan12Features`.
+unsafe fn chained_promoted_features(mut next: *mut vk::BaseOutStructure) -> Option<vk::StructureType> {
+	const PROMOTED: [vk::StructureType; 21] = [
+		vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_1_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_2_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_16BIT_STORAGE_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_MULTIVIEW_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_VARIABLE_POINTERS_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_PROTECTED_MEMORY_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_SAMPLER_YCBCR_CONVERSION_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_SHADER_DRAW_PARAMETERS_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_8BIT_STORAGE_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_SHADER_ATOMIC_INT64_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_SHADER_FLOAT16_INT8_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_DESCRIPTOR_INDEXING_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_SCALAR_BLOCK_LAYOUT_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_IMAGELESS_FRAMEBUFFER_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_UNIFORM_BUFFER_STANDARD_LAYOUT_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_SHADER_SUBGROUP_EXTENDED_TYPES_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_SEPARATE_DEPTH_STENCIL_LAYOUTS_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_HOST_QUERY_RESET_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_TIMELINE_SEMAPHORE_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_BUFFER_DEVICE_ADDRESS_FEATURES,
+		vk::StructureType::PHYSICAL_DEVICE_VULKAN_MEMORY_MODEL_FEATURES,
+	];
+	while !next.is_null() {
+		if PROMOTED.contains(&(*next).s_type) {
+			return Some((*next).s_type);
+		}
+		next = (*next).p_next;
+	}
+	None
+}
+
+/// Whether a `PhysicalDeviceBufferDeviceAddressFeatures` in the `push_next` chain enables `bufferDeviceAddress`.
+unsafe fn chained_buffer_device_address(mut next: *mut vk::BaseOutStructure) -> bool {
+	while !next.is_null() {
+		if (*next).s_type == vk::StructureType::PHYSICAL_DEVICE_BUFFER_DEVICE_ADDRESS_FEATURES {
+			let features = &*(next as *const vk::PhysicalDeviceBufferDeviceAddressFeatures);
+			return features.buffer_device_address == vk::TRUE;
+		}
+		next = (*next).p_next;
+	}
+	false
+}
+
+/// Describes which queues `Device::new` should create.
+#[derive(Default)]
+pub struct QueueRequest<'a> {
+	graphics: bool,
+	present: Option<vk::SurfaceKHR>,
+	compute: bool,
+	transfer: bool,
+	_surface: PhantomData<&'a ()>,
+}
+impl<'a> QueueRequest<'a> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Prefers a family without graphics support, falling back to the graphics family.
+	pub fn compute(mut self) -> Self {
+		self.compute = true;
+		self
+	}
+
+	pub fn graphics(mut self) -> Self {
+		self.graphics = true;
+		self
+	}
+
+	/// Requests a graphics queue that can also present to `surface`.
+	pub fn present<T>(mut self, surface: &'a Surface<T>) -> Self {
+		self.graphics = true;
+		self.present = Some(surface.vk);
+		self
+	}
+
+	/// Prefers a family without graphics or compute support, falling back to the compute, then graphics family.
+	pub fn transfer(mut self) -> Self {
+		self.transfer = true;
+		self
+	}
+}
+
+/// Queues created for each requested role. Roles may share a queue if the device doesn't have enough.
+pub struct Queues {
+	pub graphics: Option<Arc<Queue>>,
+	pub compute: Option<Arc<Queue>>,
+	pub transfer: Option<Arc<Queue>>,
+}
+
 pub struct Queue {
 	pub(crate) device: Arc<Device>,
 	family: QueueFamily,
@@ -117,6 +501,16 @@ impl Queue {
 		&self.family
 	}
 
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
+	}
+
+	/// Blocks until all work submitted to this queue has completed.
+	pub fn wait_idle(&self) -> Result<()> {
+		self.device.check_lost()?;
+		self.device.check(unsafe { self.device.vk.queue_wait_idle(self.vk) })
+	}
+
 	pub fn submit(self: &Arc<Self>, cmd: Arc<CommandBuffer<B0>>) -> SubmitFuture {
 		assert!(cmd.pool.queue_family == self.family);
 		SubmitFuture { queue: self.clone(), cmd }
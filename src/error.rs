@@ -0,0 +1,130 @@
+use crate::{image::Format, instance::Version, LoadingError, VkResult};
+use ash::vk::StructureType;
+use std::{error, ffi::NulError, fmt, result};
+use vk_mem::ErrorKind;
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+	OutOfHostMemory,
+	OutOfDeviceMemory,
+	DeviceLost,
+	SurfaceLost,
+	OutOfDate,
+	Allocator(vk_mem::Error),
+	/// A size or count was larger than the named device limit allows.
+	LimitExceeded { limit: &'static str, value: u64, max: u64 },
+	Loading(LoadingError),
+	/// An offset or size, such as "texel buffer offset", that wasn't a multiple of the alignment it requires.
+	Misaligned { what: &'static str, value: u64, alignment: u64 },
+	MissingExtensions(Vec<String>),
+	MissingFeatures(Vec<&'static str>),
+	MissingLayers(Vec<String>),
+	/// The role, such as "graphics", that no queue family could fill.
+	MissingQueueFamily(&'static str),
+	/// A buffer usage, such as "SHADER_DEVICE_ADDRESS", that the buffer wasn't created with.
+	MissingUsage(&'static str),
+	/// Name of each rejected device, with the reasons it was rejected.
+	NoSuitablePhysicalDevice(Vec<(String, Vec<String>)>),
+	NotHostVisible,
+	/// A name passed to Vulkan contained an interior NUL byte.
+	NulByte(NulError),
+	/// The current frame of a `UniformRing` had no room left.
+	OutOfRingSpace,
+	/// A struct chained with `DeviceBuilder::push_next` on a Vulkan 1.2 device whose features must be enabled through
+	/// `Features::vulkan11` or `Features::vulkan12` instead.
+	PromotedFeatures(StructureType),
+	UnsupportedFeature(String),
+	UnsupportedFormat(Format),
+	UnsupportedVersion { requested: Version, supported: Version },
+	/// `Surface::new` was given a window from a platform it can't create surfaces for.
+	UnsupportedWindowHandle,
+	Vk(VkResult),
+}
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::OutOfHostMemory => write!(f, "out of host memory"),
+			Self::OutOfDeviceMemory => write!(f, "out of device memory"),
+			Self::DeviceLost => write!(f, "device lost"),
+			Self::SurfaceLost => write!(f, "surface lost"),
+			Self::OutOfDate => write!(f, "swapchain out of date"),
+			Self::Allocator(err) => write!(f, "allocator error: {}", err),
+			Self::LimitExceeded { limit, value, max } => write!(f, "{} exceeds {} of {}", value, limit, max),
+			Self::Loading(err) => write!(f, "{}", err),
+			Self::Misaligned { what, value, alignment } => {
+				write!(f, "{} {} is not a multiple of {}", what, value, alignment)
+			},
+			Self::MissingExtensions(names) => write!(f, "missing required extensions: {}", names.join(", ")),
+			Self::MissingFeatures(names) => write!(f, "missing required features: {}", names.join(", ")),
+			Self::MissingLayers(names) => write!(f, "missing required layers: {}", names.join(", ")),
+			Self::MissingQueueFamily(role) => write!(f, "no queue family supports {}", role),
+			Self::MissingUsage(usage) => write!(f, "buffer was not created with {} usage", usage),
+			Self::NoSuitablePhysicalDevice(rejected) => {
+				if rejected.is_empty() {
+					return write!(f, "no physical devices found");
+				}
+				write!(f, "no suitable physical device")?;
+				for (name, reasons) in rejected {
+					write!(f, "\n{}: {}", name, reasons.join("; "))?;
+				}
+				Ok(())
+			},
+			Self::NotHostVisible => write!(f, "buffer is not host-visible"),
+			Self::NulByte(err) => write!(f, "{}", err),
+			Self::OutOfRingSpace => write!(f, "uniform ring frame is full"),
+			Self::PromotedFeatures(s_type) => {
+				write!(f, "{:?} can't be chained on Vulkan 1.2 devices, use Features::vulkan11 or vulkan12", s_type)
+			},
+			Self::UnsupportedFeature(feature) => write!(f, "unsupported feature: {}", feature),
+			Self::UnsupportedFormat(format) => write!(f, "unsupported format: {:?}", format),
+			Self::UnsupportedVersion { requested, supported } => {
+				write!(f, "requested Vulkan {} but only {} is supported", requested, supported)
+			},
+			Self::UnsupportedWindowHandle => write!(f, "unsupported window handle"),
+			Self::Vk(err) => write!(f, "{}", err),
+		}
+	}
+}
+impl error::Error for Error {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Self::Allocator(err) => Some(err),
+			Self::Loading(err) => Some(err),
+			Self::NulByte(err) => Some(err),
+			Self::Vk(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+impl From<VkResult> for Error {
+	fn from(val: VkResult) -> Self {
+		match val {
+			VkResult::ERROR_OUT_OF_HOST_MEMORY => Self::OutOfHostMemory,
+			VkResult::ERROR_OUT_OF_DEVICE_MEMORY => Self::OutOfDeviceMemory,
+			VkResult::ERROR_DEVICE_LOST => Self::DeviceLost,
+			VkResult::ERROR_SURFACE_LOST_KHR => Self::SurfaceLost,
+			VkResult::ERROR_OUT_OF_DATE_KHR => Self::OutOfDate,
+			val => Self::Vk(val),
+		}
+	}
+}
+impl From<vk_mem::Error> for Error {
+	fn from(val: vk_mem::Error) -> Self {
+		match val.kind() {
+			ErrorKind::Vulkan(err) => Self::from(*err),
+			_ => Self::Allocator(val),
+		}
+	}
+}
+impl From<NulError> for Error {
+	fn from(val: NulError) -> Self {
+		Self::NulByte(val)
+	}
+}
+impl From<LoadingError> for Error {
+	fn from(val: LoadingError) -> Self {
+		Self::Loading(val)
+	}
+}
@@ -1,11 +1,15 @@
 pub use ash::vk::{
-	ClearColorValue, Format, ImageAspectFlags, ImageLayout, ImageSubresourceRange, ImageType, ImageUsageFlags,
+	ClearColorValue, ComponentMapping, ComponentSwizzle, Filter, Format, ImageAspectFlags, ImageCreateFlags,
+	ImageLayout, ImageSubresourceLayers, ImageSubresourceRange, ImageType, ImageUsageFlags, ImageViewType,
+	SampleCountFlags,
 };
 
 use crate::{
 	buffer::Buffer,
-	command::{CommandPool, ImageMemoryBarrier, PipelineStageFlags},
+	command::{layout_access, CommandPool, ImageMemoryBarrier, PipelineStageFlags},
 	device::{Device, Queue, SubmitFuture},
+	error::{Error, Result},
+	physical_device::FormatFeatureFlags,
 	render_pass::RenderPass,
 };
 use ash::{version::DeviceV1_0, vk};
@@ -13,6 +17,7 @@ use atomic::Atomic;
 use nalgebra::Vector3;
 use std::{
 	iter::once,
+	result,
 	sync::{atomic::Ordering, Arc},
 };
 use vk_mem::{Allocation, AllocationCreateInfo, MemoryUsage};
@@ -21,42 +26,68 @@ pub struct Image {
 	device: Arc<Device>,
 	pub(crate) vk: vk::Image,
 	alloc: Allocation,
+	image_type: ImageType,
 	size: Vector3<u32>,
+	format: Format,
+	mip_levels: u32,
+	array_layers: u32,
+	samples: SampleCountFlags,
+	usage: ImageUsageFlags,
+	flags: ImageCreateFlags,
 	layout: Atomic<ImageLayout>,
 }
 impl Image {
+	/// Sets the extent, format and usage, leaving the rest to `ImageBuilder`.
+	pub fn builder(
+		device: Arc<Device>,
+		image_type: ImageType,
+		width: u32,
+		height: u32,
+		depth: u32,
+		format: Format,
+		usage: ImageUsageFlags,
+	) -> ImageBuilder {
+		ImageBuilder {
+			device,
+			image_type,
+			size: Vector3::new(width, height, depth),
+			format,
+			usage,
+			mip_levels: 1,
+			array_layers: 1,
+			samples: SampleCountFlags::TYPE_1,
+			flags: ImageCreateFlags::empty(),
+		}
+	}
+
+	/// Panics if `mip_levels` is 0 or more than a full mip chain. Use `builder` for multisampled or layered images.
 	pub fn init(
 		device: Arc<Device>,
 		image_type: ImageType,
 		width: u32,
 		height: u32,
 		depth: u32,
+		mip_levels: u32,
 		format: Format,
 		usage: ImageUsageFlags,
-	) -> ImageInit {
-		let extent = vk::Extent3D::builder().width(width).height(height).depth(depth).build();
-		let layout = ImageLayout::UNDEFINED;
-		let ci = vk::ImageCreateInfo::builder()
-			.image_type(image_type)
-			.format(format)
-			.extent(extent)
-			.mip_levels(1)
-			.array_layers(1)
-			.samples(vk::SampleCountFlags::TYPE_1)
-			.tiling(vk::ImageTiling::OPTIMAL)
-			.usage(usage)
-			.sharing_mode(vk::SharingMode::EXCLUSIVE)
-			.initial_layout(layout);
+	) -> Result<ImageInit> {
+		Self::builder(device, image_type, width, height, depth, format, usage).mip_levels(mip_levels).build()
+	}
 
-		let usage = MemoryUsage::GpuOnly;
-		let aci = AllocationCreateInfo { usage, ..Default::default() };
+	pub fn array_layers(&self) -> u32 {
+		self.array_layers
+	}
 
-		let (vk, alloc, _) = device.allocator.create_image(&ci, &aci).unwrap();
+	pub fn flags(&self) -> ImageCreateFlags {
+		self.flags
+	}
 
-		let size = Vector3::new(width, height, depth);
+	pub fn format(&self) -> Format {
+		self.format
+	}
 
-		let buf = Arc::new(Self { device, vk, alloc, size, layout: Atomic::new(layout) });
-		ImageInit::new(buf)
+	pub fn image_type(&self) -> ImageType {
+		self.image_type
 	}
 
 	pub fn size(&self) -> &Vector3<u32> {
@@ -67,6 +98,27 @@ impl Image {
 		self.layout.load(Ordering::Relaxed)
 	}
 
+	pub fn mip_levels(&self) -> u32 {
+		self.mip_levels
+	}
+
+	/// Size of mip level `level`.
+	pub fn mip_size(&self, level: u32) -> Vector3<u32> {
+		self.size.map(|x| (x >> level).max(1))
+	}
+
+	pub fn samples(&self) -> SampleCountFlags {
+		self.samples
+	}
+
+	pub fn usage(&self) -> ImageUsageFlags {
+		self.usage
+	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
+	}
+
 	pub fn set_layout(&self, layout: ImageLayout) {
 		self.layout.store(layout, Ordering::Relaxed)
 	}
@@ -75,6 +127,47 @@ impl Image {
 		self.size.x as u64 * self.size.y as u64 * self.size.z as u64
 	}
 }
+impl Image {
+	/// A blit from mip level `level - 1` to `level`, across every layer.
+	fn mip_blit(&self, level: u32) -> vk::ImageBlit {
+		let subresource = |level| {
+			vk::ImageSubresourceLayers::builder()
+				.aspect_mask(ImageAspectFlags::COLOR)
+				.mip_level(level)
+				.base_array_layer(0)
+				.layer_count(self.array_layers)
+				.build()
+		};
+		let corner = |level| {
+			let size = self.mip_size(level);
+			vk::Offset3D { x: size.x as i32, y: size.y as i32, z: size.z as i32 }
+		};
+		vk::ImageBlit::builder()
+			.src_subresource(subresource(level - 1))
+			.src_offsets([vk::Offset3D::default(), corner(level - 1)])
+			.dst_subresource(subresource(level))
+			.dst_offsets([vk::Offset3D::default(), corner(level)])
+			.build()
+	}
+
+	/// A barrier transitioning only mip level `level`, which the image-wide layout tracking can't express.
+	fn mip_barrier(&self, level: u32, old_layout: ImageLayout, new_layout: ImageLayout) -> vk::ImageMemoryBarrier {
+		let subresource_range = vk::ImageSubresourceRange::builder()
+			.aspect_mask(ImageAspectFlags::COLOR)
+			.base_mip_level(level)
+			.level_count(1)
+			.layer_count(vk::REMAINING_ARRAY_LAYERS)
+			.build();
+		vk::ImageMemoryBarrier::builder()
+			.src_access_mask(layout_access(old_layout))
+			.dst_access_mask(layout_access(new_layout))
+			.old_layout(old_layout)
+			.new_layout(new_layout)
+			.image(self.vk)
+			.subresource_range(subresource_range)
+			.build()
+	}
+}
 impl ImageAbstract for Image {
 	fn device(&self) -> &Arc<Device> {
 		&self.device
@@ -91,6 +184,112 @@ impl Drop for Image {
 	}
 }
 
+pub struct ImageBuilder {
+	device: Arc<Device>,
+	image_type: ImageType,
+	size: Vector3<u32>,
+	format: Format,
+	usage: ImageUsageFlags,
+	mip_levels: u32,
+	array_layers: u32,
+	samples: SampleCountFlags,
+	flags: ImageCreateFlags,
+}
+impl ImageBuilder {
+	pub fn array_layers(mut self, array_layers: u32) -> Self {
+		self.array_layers = array_layers;
+		self
+	}
+
+	/// Fails with `Error::UnsupportedFormat` if the device doesn't support the format with this type, usage and flags,
+	/// or at this size, mip level count, layer count or sample count. Panics if the parameters contradict each other,
+	/// such as a cube compatible image that isn't square, or if `mip_levels` is more than a full mip chain.
+	pub fn build(self) -> Result<ImageInit> {
+		let (width, height, depth) = (self.size.x, self.size.y, self.size.z);
+		assert!(self.array_layers > 0);
+		assert!(self.mip_levels > 0 && self.mip_levels <= 32 - width.max(height).max(depth).leading_zeros());
+		match self.image_type {
+			ImageType::TYPE_1D => assert!(height == 1 && depth == 1),
+			ImageType::TYPE_2D => assert!(depth == 1),
+			_ => assert!(self.array_layers == 1),
+		}
+		if self.flags.contains(ImageCreateFlags::CUBE_COMPATIBLE) {
+			assert!(self.image_type == ImageType::TYPE_2D && width == height && self.array_layers >= 6);
+		}
+		if self.samples != SampleCountFlags::TYPE_1 {
+			assert!(self.image_type == ImageType::TYPE_2D && self.mip_levels == 1);
+			assert!(!self.flags.contains(ImageCreateFlags::CUBE_COMPATIBLE));
+		}
+
+		let tiling = vk::ImageTiling::OPTIMAL;
+		let physical_device = self.device.physical_device();
+		let props =
+			physical_device.get_image_format_properties(self.format, self.image_type, tiling, self.usage, self.flags)?;
+		let max = props.max_extent;
+		let supported = width <= max.width
+			&& height <= max.height
+			&& depth <= max.depth
+			&& self.mip_levels <= props.max_mip_levels
+			&& self.array_layers <= props.max_array_layers
+			&& props.sample_counts.contains(self.samples);
+		if !supported {
+			return Err(Error::UnsupportedFormat(self.format));
+		}
+
+		let extent = vk::Extent3D::builder().width(width).height(height).depth(depth).build();
+		let layout = ImageLayout::UNDEFINED;
+		let ci = vk::ImageCreateInfo::builder()
+			.flags(self.flags)
+			.image_type(self.image_type)
+			.format(self.format)
+			.extent(extent)
+			.mip_levels(self.mip_levels)
+			.array_layers(self.array_layers)
+			.samples(self.samples)
+			.tiling(tiling)
+			.usage(self.usage)
+			.sharing_mode(vk::SharingMode::EXCLUSIVE)
+			.initial_layout(layout);
+
+		let aci = AllocationCreateInfo { usage: MemoryUsage::GpuOnly, ..Default::default() };
+
+		self.device.check_lost()?;
+		let (vk, alloc, _) = self.device.check(self.device.allocator.create_image(&ci, &aci))?;
+
+		let img = Arc::new(Image {
+			device: self.device,
+			vk,
+			alloc,
+			image_type: self.image_type,
+			size: self.size,
+			format: self.format,
+			mip_levels: self.mip_levels,
+			array_layers: self.array_layers,
+			samples: self.samples,
+			usage: self.usage,
+			flags: self.flags,
+			layout: Atomic::new(layout),
+		});
+		Ok(ImageInit::new(img))
+	}
+
+	/// `CUBE_COMPATIBLE` allows cube map views, and `MUTABLE_FORMAT` views with another format.
+	pub fn flags(mut self, flags: ImageCreateFlags) -> Self {
+		self.flags = flags;
+		self
+	}
+
+	pub fn mip_levels(mut self, mip_levels: u32) -> Self {
+		self.mip_levels = mip_levels;
+		self
+	}
+
+	pub fn samples(mut self, samples: SampleCountFlags) -> Self {
+		self.samples = samples;
+		self
+	}
+}
+
 pub struct ImageInit {
 	pub(crate) img: Arc<Image>,
 }
@@ -105,9 +304,9 @@ impl ImageInit {
 		queue: &Arc<Queue>,
 		pool: &Arc<CommandPool>,
 		color: ClearColorValue,
-	) -> (Arc<Image>, SubmitFuture) {
+	) -> Result<(Arc<Image>, SubmitFuture)> {
 		let cmd = pool
-			.record(true, false)
+			.record(true, false)?
 			.pipeline_barrier(
 				PipelineStageFlags::TOP_OF_PIPE,
 				PipelineStageFlags::TRANSFER,
@@ -119,9 +318,9 @@ impl ImageInit {
 				PipelineStageFlags::FRAGMENT_SHADER | PipelineStageFlags::VERTEX_SHADER,
 				once(ImageMemoryBarrier::new(self.img.clone(), ImageLayout::SHADER_READ_ONLY_OPTIMAL)),
 			)
-			.build();
+			.build()?;
 		let future = queue.submit(cmd);
-		(self.img, future)
+		Ok((self.img, future))
 	}
 
 	pub fn copy_from_buffer<T: Send + Sync + 'static>(
@@ -129,9 +328,9 @@ impl ImageInit {
 		queue: &Arc<Queue>,
 		pool: &Arc<CommandPool>,
 		buffer: Arc<Buffer<[T]>>,
-	) -> (Arc<Image>, SubmitFuture) {
+	) -> Result<(Arc<Image>, SubmitFuture)> {
 		let cmd = pool
-			.record(true, false)
+			.record(true, false)?
 			.pipeline_barrier(
 				PipelineStageFlags::TOP_OF_PIPE,
 				PipelineStageFlags::TRANSFER,
@@ -143,9 +342,64 @@ impl ImageInit {
 				PipelineStageFlags::FRAGMENT_SHADER | PipelineStageFlags::VERTEX_SHADER,
 				once(ImageMemoryBarrier::new(self.img.clone(), ImageLayout::SHADER_READ_ONLY_OPTIMAL)),
 			)
-			.build();
+			.build()?;
 		let future = queue.submit(cmd);
-		(self.img, future)
+		Ok((self.img, future))
+	}
+
+	/// Copies `buffer` to mip level 0, then blits each level down to the next. Returns `Error::UnsupportedFormat`
+	/// along with `self` if the format can't be blitted with linear filtering, so the caller can fall back to
+	/// `copy_from_buffer`. Panics unless the image has `TRANSFER_SRC` and `TRANSFER_DST` usage and a single array
+	/// layer, since only layer 0 is filled from `buffer`.
+	pub fn generate_mipmaps<T: Send + Sync + 'static>(
+		self,
+		queue: &Arc<Queue>,
+		pool: &Arc<CommandPool>,
+		buffer: Arc<Buffer<[T]>>,
+	) -> result::Result<(Arc<Image>, SubmitFuture), (Error, ImageInit)> {
+		assert!(self.img.usage.contains(ImageUsageFlags::TRANSFER_SRC | ImageUsageFlags::TRANSFER_DST));
+		assert!(self.img.array_layers == 1);
+
+		let required = FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+			| FormatFeatureFlags::BLIT_SRC
+			| FormatFeatureFlags::BLIT_DST;
+		let features = self.img.device.physical_device().get_format_properties(self.img.format).optimal_tiling_features;
+		if !features.contains(required) {
+			return Err((Error::UnsupportedFormat(self.img.format), self));
+		}
+
+		let img = self.img.clone();
+		let shader_stages = PipelineStageFlags::FRAGMENT_SHADER | PipelineStageFlags::VERTEX_SHADER;
+		let cmd = match pool.record(true, false) {
+			Ok(cmd) => cmd,
+			Err(err) => return Err((err, self)),
+		};
+		let mut cmd = cmd
+			.pipeline_barrier(
+				PipelineStageFlags::TOP_OF_PIPE,
+				PipelineStageFlags::TRANSFER,
+				once(ImageMemoryBarrier::new(img.clone(), ImageLayout::TRANSFER_DST_OPTIMAL)),
+			)
+			.copy_buffer_to_image(buffer, img.clone(), &img.size);
+		for level in 1..img.mip_levels {
+			let src = level - 1;
+			let to_src = img.mip_barrier(src, ImageLayout::TRANSFER_DST_OPTIMAL, ImageLayout::TRANSFER_SRC_OPTIMAL);
+			let done = img.mip_barrier(src, ImageLayout::TRANSFER_SRC_OPTIMAL, ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+			cmd = cmd
+				.barrier(PipelineStageFlags::TRANSFER, PipelineStageFlags::TRANSFER, &[], &[to_src], None)
+				.blit_image(img.clone(), img.clone(), &[img.mip_blit(level)], Filter::LINEAR)
+				.barrier(PipelineStageFlags::TRANSFER, shader_stages, &[], &[done], None);
+		}
+		let last = img.mip_levels - 1;
+		let done = img.mip_barrier(last, ImageLayout::TRANSFER_DST_OPTIMAL, ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+		let cmd = match cmd.barrier(PipelineStageFlags::TRANSFER, shader_stages, &[], &[done], None).build() {
+			Ok(cmd) => cmd,
+			Err(err) => return Err((err, self)),
+		};
+		img.set_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+		let future = queue.submit(cmd);
+		Ok((img, future))
 	}
 }
 
@@ -161,7 +415,7 @@ impl Framebuffer {
 		attachments: Vec<Arc<ImageView>>,
 		width: u32,
 		height: u32,
-	) -> Arc<Framebuffer> {
+	) -> Result<Arc<Framebuffer>> {
 		let attachment_vks: Vec<_> = attachments.iter().map(|x| x.vk).collect();
 
 		let ci = vk::FramebufferCreateInfo::builder()
@@ -170,8 +424,13 @@ impl Framebuffer {
 			.width(width)
 			.height(height)
 			.layers(1);
-		let vk = unsafe { device.vk.create_framebuffer(&ci, None) }.unwrap();
-		Arc::new(Self { render_pass, _attachments: attachments, vk })
+		device.check_lost()?;
+		let vk = device.check(unsafe { device.vk.create_framebuffer(&ci, None) })?;
+		Ok(Arc::new(Self { render_pass, _attachments: attachments, vk }))
+	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.render_pass.device().set_object_name(self.vk, name)
 	}
 }
 impl Drop for Framebuffer {
@@ -189,14 +448,42 @@ impl ImageView {
 		image: Arc<dyn ImageAbstract + Send + Sync>,
 		format: Format,
 		subresource_range: ImageSubresourceRange,
-	) -> Arc<ImageView> {
+	) -> Result<Arc<ImageView>> {
 		let ci = vk::ImageViewCreateInfo::builder()
 			.image(image.vk())
 			.view_type(vk::ImageViewType::TYPE_2D)
 			.format(format)
 			.subresource_range(subresource_range);
-		let vk = unsafe { image.device().vk.create_image_view(&ci, None) }.unwrap();
-		Arc::new(Self { image, vk })
+		image.device().check_lost()?;
+		let vk = image.device().check(unsafe { image.device().vk.create_image_view(&ci, None) })?;
+		Ok(Arc::new(Self { image, vk }))
+	}
+
+	/// Views the whole of `image` with its own format, and a view type matching its type and layer count.
+	pub fn builder(image: Arc<Image>) -> ImageViewBuilder {
+		let view_type = match (image.image_type, image.array_layers) {
+			(ImageType::TYPE_1D, 1) => ImageViewType::TYPE_1D,
+			(ImageType::TYPE_1D, _) => ImageViewType::TYPE_1D_ARRAY,
+			(ImageType::TYPE_2D, 1) => ImageViewType::TYPE_2D,
+			(ImageType::TYPE_2D, _) => ImageViewType::TYPE_2D_ARRAY,
+			_ => ImageViewType::TYPE_3D,
+		};
+		let subresource_range = vk::ImageSubresourceRange::builder()
+			.aspect_mask(format_aspect(image.format))
+			.level_count(vk::REMAINING_MIP_LEVELS)
+			.layer_count(vk::REMAINING_ARRAY_LAYERS)
+			.build();
+		ImageViewBuilder {
+			format: image.format,
+			image,
+			view_type,
+			components: ComponentMapping::default(),
+			subresource_range,
+		}
+	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.image.device().set_object_name(self.vk, name)
 	}
 }
 impl ImageAbstract for ImageView {
@@ -214,12 +501,100 @@ impl Drop for ImageView {
 	}
 }
 
+pub struct ImageViewBuilder {
+	image: Arc<Image>,
+	view_type: ImageViewType,
+	format: Format,
+	components: ComponentMapping,
+	subresource_range: ImageSubresourceRange,
+}
+impl ImageViewBuilder {
+	/// Either of `vk::REMAINING_ARRAY_LAYERS` and an exact count can be used.
+	pub fn array_layers(mut self, base_array_layer: u32, layer_count: u32) -> Self {
+		self.subresource_range.base_array_layer = base_array_layer;
+		self.subresource_range.layer_count = layer_count;
+		self
+	}
+
+	/// Defaults to depth for depth formats, stencil for stencil-only formats, and color otherwise.
+	pub fn aspect_mask(mut self, aspect_mask: ImageAspectFlags) -> Self {
+		self.subresource_range.aspect_mask = aspect_mask;
+		self
+	}
+
+	/// Panics if the view doesn't fit the image: the subresources must exist, the view type must suit the image type
+	/// and layer count, and another format needs `MUTABLE_FORMAT`. Cube map arrays need the `image_cube_array`
+	/// feature.
+	pub fn build(self) -> Result<Arc<ImageView>> {
+		let img = &self.image;
+		let range = &self.subresource_range;
+		let level_count = resolve_count(range.level_count, range.base_mip_level, img.mip_levels);
+		let layer_count = resolve_count(range.layer_count, range.base_array_layer, img.array_layers);
+		assert!(level_count > 0 && range.base_mip_level as u64 + level_count as u64 <= img.mip_levels as u64);
+		assert!(layer_count > 0 && range.base_array_layer as u64 + layer_count as u64 <= img.array_layers as u64);
+
+		if self.format != img.format {
+			assert!(img.flags.contains(ImageCreateFlags::MUTABLE_FORMAT));
+		}
+
+		let cube = img.image_type == ImageType::TYPE_2D && img.flags.contains(ImageCreateFlags::CUBE_COMPATIBLE);
+		let fits = match self.view_type {
+			ImageViewType::TYPE_1D => img.image_type == ImageType::TYPE_1D && layer_count == 1,
+			ImageViewType::TYPE_1D_ARRAY => img.image_type == ImageType::TYPE_1D,
+			ImageViewType::TYPE_2D => img.image_type == ImageType::TYPE_2D && layer_count == 1,
+			ImageViewType::TYPE_2D_ARRAY => img.image_type == ImageType::TYPE_2D,
+			ImageViewType::CUBE => cube && layer_count == 6,
+			ImageViewType::CUBE_ARRAY => cube && layer_count.is_multiple_of(6),
+			ImageViewType::TYPE_3D => img.image_type == ImageType::TYPE_3D,
+			_ => false,
+		};
+		assert!(fits);
+		let cube_array = img.device.enabled_features().core().image_cube_array == vk::TRUE;
+		if self.view_type == ImageViewType::CUBE_ARRAY && !cube_array {
+			return Err(Error::MissingFeatures(vec!["image_cube_array"]));
+		}
+
+		let ci = vk::ImageViewCreateInfo::builder()
+			.image(img.vk)
+			.view_type(self.view_type)
+			.format(self.format)
+			.components(self.components)
+			.subresource_range(self.subresource_range);
+		img.device.check_lost()?;
+		let vk = img.device.check(unsafe { img.device.vk.create_image_view(&ci, None) })?;
+		Ok(Arc::new(ImageView { image: self.image, vk }))
+	}
+
+	pub fn components(mut self, components: ComponentMapping) -> Self {
+		self.components = components;
+		self
+	}
+
+	/// A format other than the image's needs `MUTABLE_FORMAT`.
+	pub fn format(mut self, format: Format) -> Self {
+		self.format = format;
+		self
+	}
+
+	/// Either of `vk::REMAINING_MIP_LEVELS` and an exact count can be used.
+	pub fn mip_levels(mut self, base_mip_level: u32, level_count: u32) -> Self {
+		self.subresource_range.base_mip_level = base_mip_level;
+		self.subresource_range.level_count = level_count;
+		self
+	}
+
+	pub fn view_type(mut self, view_type: ImageViewType) -> Self {
+		self.view_type = view_type;
+		self
+	}
+}
+
 pub struct Sampler {
 	device: Arc<Device>,
 	pub(crate) vk: vk::Sampler,
 }
 impl Sampler {
-	pub fn new(device: Arc<Device>) -> Arc<Self> {
+	pub fn new(device: Arc<Device>) -> Result<Arc<Self>> {
 		let ci = vk::SamplerCreateInfo::builder()
 			.mag_filter(vk::Filter::NEAREST)
 			.min_filter(vk::Filter::NEAREST)
@@ -227,8 +602,13 @@ impl Sampler {
 			.address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
 			.address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
 			.address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
-		let vk = unsafe { device.vk.create_sampler(&ci, None) }.unwrap();
-		Arc::new(Self { device, vk })
+		device.check_lost()?;
+		let vk = device.check(unsafe { device.vk.create_sampler(&ci, None) })?;
+		Ok(Arc::new(Self { device, vk }))
+	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
 	}
 }
 impl Drop for Sampler {
@@ -237,6 +617,67 @@ impl Drop for Sampler {
 	}
 }
 
+/// The aspect views of `format` default to.
+fn format_aspect(format: Format) -> ImageAspectFlags {
+	match format {
+		Format::S8_UINT => ImageAspectFlags::STENCIL,
+		Format::D16_UNORM
+		| Format::X8_D24_UNORM_PACK32
+		| Format::D32_SFLOAT
+		| Format::D16_UNORM_S8_UINT
+		| Format::D24_UNORM_S8_UINT
+		| Format::D32_SFLOAT_S8_UINT => ImageAspectFlags::DEPTH,
+		_ => ImageAspectFlags::COLOR,
+	}
+}
+
+/// `count`, or the levels or layers from `base` to `total` for `REMAINING_MIP_LEVELS` or `REMAINING_ARRAY_LAYERS`,
+/// which are both `!0`.
+fn resolve_count(count: u32, base: u32, total: u32) -> u32 {
+	if count == vk::REMAINING_MIP_LEVELS {
+		total.saturating_sub(base)
+	} else {
+		count
+	}
+}
+
+/// Bytes per texel block, and the block's width and height, of the `aspect` of `format` as laid out in a buffer.
+/// `None` for formats not covered here, such as ASTC and multi-planar formats.
+pub(crate) fn texel_block(format: Format, aspect: ImageAspectFlags) -> Option<(u64, u32, u32)> {
+	if aspect == ImageAspectFlags::STENCIL {
+		return Some((1, 1, 1));
+	}
+
+	let bytes = match format.as_raw() {
+		// R4G4, R8, S8
+		1 | 9..=15 | 127 => 1,
+		// 16-bit packed, R8G8, R16, D16, and the depth of D16_UNORM_S8_UINT
+		2..=8 | 16..=22 | 70..=76 | 124 | 128 => 2,
+		// R8G8B8, B8G8R8
+		23..=36 => 3,
+		// R8G8B8A8, B8G8R8A8, 32-bit packed, R16G16, R32, X8_D24, D32, and the depth of D24S8 and D32S8
+		37..=69 | 77..=83 | 98..=100 | 122..=123 | 125..=126 | 129..=130 => 4,
+		// R16G16B16
+		84..=90 => 6,
+		// R16G16B16A16, R32G32, R64
+		91..=97 | 101..=103 | 110..=112 => 8,
+		// R32G32B32
+		104..=106 => 12,
+		// R32G32B32A32, R64G64
+		107..=109 | 113..=115 => 16,
+		// R64G64B64
+		116..=118 => 24,
+		// R64G64B64A64
+		119..=121 => 32,
+		// BC1, BC4, ETC2 RGB and RGBA1, EAC R11
+		131..=134 | 139..=140 | 147..=150 | 153..=154 => return Some((8, 4, 4)),
+		// BC2, BC3, BC5, BC6H, BC7, ETC2 RGBA8, EAC R11G11
+		135..=138 | 141..=146 | 151..=152 | 155..=156 => return Some((16, 4, 4)),
+		_ => return None,
+	};
+	Some((bytes, 1, 1))
+}
+
 pub trait ImageAbstract {
 	fn device(&self) -> &Arc<Device>;
 	fn vk(&self) -> vk::Image;
@@ -1,67 +1,234 @@
-use crate::Vulkan;
+pub use ash::vk::{
+	DebugUtilsMessageSeverityFlagsEXT as DebugMessageSeverity, DebugUtilsMessageTypeFlagsEXT as DebugMessageType,
+};
+
+use crate::{
+	error::{Error, Result},
+	Vulkan,
+};
 use ash::{
-	extensions::khr,
+	extensions::{ext, khr},
 	version::{EntryV1_0, InstanceV1_0},
-	vk, Instance as VkInstance,
+	vk, Instance as VkInstance, InstanceError,
 };
+use log::{debug, error, log, Level};
 use std::{
 	collections::HashSet,
-	ffi::{CStr, CString},
+	ffi::{c_void, CStr, CString},
+	fmt, mem,
+	panic::{self, AssertUnwindSafe},
+	ptr, result,
 	sync::Arc,
 };
 
+pub type DebugCallback = Box<dyn Fn(DebugMessageSeverity, DebugMessageType, &str) + Send + Sync>;
+
+const VALIDATION_LAYER: &[u8] = b"VK_LAYER_KHRONOS_validation\0";
+
 pub struct Instance {
 	_vulkan: Arc<Vulkan>,
 	pub vk: VkInstance,
 	pub khr_surface: khr::Surface,
 	#[cfg(windows)]
-	pub khr_win32_surface: khr::Win32Surface,
+	pub khr_win32_surface: Option<khr::Win32Surface>,
 	#[cfg(unix)]
-	pub khr_xlib_surface: khr::XlibSurface,
+	pub khr_xlib_surface: Option<khr::XlibSurface>,
 	#[cfg(unix)]
-	pub khr_wayland_surface: khr::WaylandSurface,
+	pub khr_wayland_surface: Option<khr::WaylandSurface>,
+	pub ext_debug_utils: Option<ext::DebugUtils>,
+	pub ext_headless_surface: Option<vk::ExtHeadlessSurfaceFn>,
+	debug_messenger: vk::DebugUtilsMessengerEXT,
+	// boxed twice so the pointer handed to the messenger stays valid
+	_debug_callback: Option<Box<DebugCallback>>,
+	api_version: Version,
+	extensions: Vec<CString>,
+	layers: Vec<CString>,
 }
 impl Instance {
-	pub fn new(vulkan: Arc<Vulkan>, application_name: &str, application_version: Version) -> Arc<Self> {
-		let application_name = CString::new(application_name).unwrap();
+	pub fn new(
+		vulkan: Arc<Vulkan>,
+		application_name: &str,
+		application_version: Version,
+	) -> Result<Arc<Self>> {
+		Self::builder(vulkan, application_name, application_version).build()
+	}
+
+	pub fn builder(vulkan: Arc<Vulkan>, application_name: &str, application_version: Version) -> InstanceBuilder<'_> {
+		InstanceBuilder::new(vulkan, application_name, application_version)
+	}
+
+	pub fn api_version(&self) -> Version {
+		self.api_version
+	}
+
+	/// Every extension enabled on this instance, including optional extensions that were available.
+	pub fn enabled_extensions(&self) -> impl Iterator<Item = &CStr> {
+		self.extensions.iter().map(|x| x.as_c_str())
+	}
+
+	/// Every layer enabled on this instance, including optional layers that were available.
+	pub fn enabled_layers(&self) -> impl Iterator<Item = &CStr> {
+		self.layers.iter().map(|x| x.as_c_str())
+	}
+
+	pub fn is_extension_enabled(&self, name: &CStr) -> bool {
+		self.enabled_extensions().any(|x| x == name)
+	}
+
+	pub fn is_layer_enabled(&self, name: &CStr) -> bool {
+		self.enabled_layers().any(|x| x == name)
+	}
+}
+impl Drop for Instance {
+	fn drop(&mut self) {
+		unsafe {
+			if let Some(ext_debug_utils) = &self.ext_debug_utils {
+				if self.debug_messenger != vk::DebugUtilsMessengerEXT::null() {
+					ext_debug_utils.destroy_debug_utils_messenger(self.debug_messenger, None);
+				}
+			}
+			self.vk.destroy_instance(None);
+		}
+	}
+}
+
+pub struct InstanceBuilder<'a> {
+	vulkan: Arc<Vulkan>,
+	application_name: &'a str,
+	application_version: Version,
+	engine_name: Option<&'a str>,
+	engine_version: Version,
+	api_version: Version,
+	// (name, required)
+	extensions: Vec<(CString, bool)>,
+	layers: Vec<(CString, bool)>,
+	headless: bool,
+	validation: bool,
+	debug_callback: Option<DebugCallback>,
+}
+impl<'a> InstanceBuilder<'a> {
+	fn new(vulkan: Arc<Vulkan>, application_name: &'a str, application_version: Version) -> Self {
+		Self {
+			vulkan,
+			application_name,
+			application_version,
+			engine_name: None,
+			engine_version: Version::new(0, 0, 0),
+			api_version: Version::new(1, 0, 0),
+			extensions: vec![],
+			layers: vec![],
+			headless: false,
+			validation: false,
+			debug_callback: None,
+		}
+	}
 
-		let app_info = vk::ApplicationInfo::builder()
+	pub fn build(self) -> Result<Arc<Instance>> {
+		let vulkan = self.vulkan;
+
+		let supported_version = vulkan.vk.try_enumerate_instance_version()?.map(Version::from_vk);
+		let supported_version = supported_version.unwrap_or_else(|| Version::new(1, 0, 0));
+		if self.api_version.major() != supported_version.major() || self.api_version.minor() > supported_version.minor()
+		{
+			return Err(Error::UnsupportedVersion { requested: self.api_version, supported: supported_version });
+		}
+
+		let application_name = CString::new(self.application_name)?;
+		let engine_name = self.engine_name.map(CString::new).transpose()?;
+
+		let mut app_info = vk::ApplicationInfo::builder()
 			.application_name(&application_name)
-			.application_version(application_version.vk);
+			.application_version(self.application_version.vk)
+			.engine_version(self.engine_version.vk)
+			.api_version(self.api_version.vk);
+		if let Some(engine_name) = &engine_name {
+			app_info = app_info.engine_name(engine_name);
+		}
 
-		let mut exts = vec![b"VK_KHR_surface\0".as_ptr() as _];
-		#[cfg(windows)]
-		exts.push(b"VK_KHR_win32_surface\0".as_ptr() as _);
-		#[cfg(unix)]
-		exts.push(b"VK_KHR_xlib_surface\0".as_ptr() as _);
-
-		#[allow(unused_mut)]
-		let mut layers_pref = HashSet::new();
-		// #[cfg(debug_assertions)]
-		// layers_pref.insert(CStr::from_bytes_with_nul(b"VK_LAYER_LUNARG_monitor\0").unwrap());
-		let layers = vulkan.vk.enumerate_instance_layer_properties().unwrap();
-		let layers = layers
-			.iter()
-			.map(|props| unsafe { CStr::from_ptr(props.layer_name.as_ptr()) })
-			.collect::<HashSet<_>>()
-			.intersection(&layers_pref)
-			.map(|ext| ext.as_ptr())
-			.collect::<Vec<_>>();
-
-		let ci = vk::InstanceCreateInfo::builder()
+		let mut extensions = self.extensions;
+		let mut layers = self.layers;
+		if self.headless {
+			extensions.push((khr::Surface::name().to_owned(), false));
+			extensions.push((vk::ExtHeadlessSurfaceFn::name().to_owned(), false));
+		} else {
+			extensions.push((khr::Surface::name().to_owned(), true));
+			#[cfg(windows)]
+			extensions.push((khr::Win32Surface::name().to_owned(), true));
+			#[cfg(unix)]
+			extensions.push((khr::XlibSurface::name().to_owned(), false));
+			#[cfg(unix)]
+			extensions.push((khr::WaylandSurface::name().to_owned(), false));
+		}
+		if self.validation || self.debug_callback.is_some() {
+			extensions.push((ext::DebugUtils::name().to_owned(), false));
+		}
+		if self.validation {
+			layers.push((CStr::from_bytes_with_nul(VALIDATION_LAYER).unwrap().to_owned(), false));
+		}
+
+		let available = vulkan.vk.enumerate_instance_extension_properties()?;
+		let available =
+			available.iter().map(|props| unsafe { CStr::from_ptr(props.extension_name.as_ptr()) }).collect();
+		let extensions = negotiate("extension", &available, extensions).map_err(Error::MissingExtensions)?;
+
+		let available = vulkan.vk.enumerate_instance_layer_properties()?;
+		let available = available.iter().map(|props| unsafe { CStr::from_ptr(props.layer_name.as_ptr()) }).collect();
+		let layers = negotiate("layer", &available, layers).map_err(Error::MissingLayers)?;
+
+		let extension_ptrs: Vec<_> = extensions.iter().map(|x| x.as_ptr()).collect();
+		let layer_ptrs: Vec<_> = layers.iter().map(|x| x.as_ptr()).collect();
+
+		let debug_utils = extensions.iter().any(|x| x.as_c_str() == ext::DebugUtils::name());
+		let debug_callback = self.debug_callback.map(Box::new);
+		let user_data = debug_callback.as_ref().map(|x| &**x as *const DebugCallback as *mut c_void);
+		let mut messenger_ci = vk::DebugUtilsMessengerCreateInfoEXT::builder()
+			.message_severity(DebugMessageSeverity::all())
+			.message_type(DebugMessageType::all())
+			.pfn_user_callback(Some(debug_utils_callback))
+			.user_data(user_data.unwrap_or(ptr::null_mut()));
+
+		let mut ci = vk::InstanceCreateInfo::builder()
 			.application_info(&app_info)
-			.enabled_layer_names(&layers)
-			.enabled_extension_names(&exts);
-		let vk = unsafe { vulkan.vk.create_instance(&ci, None) }.unwrap();
+			.enabled_layer_names(&layer_ptrs)
+			.enabled_extension_names(&extension_ptrs);
+		if debug_utils {
+			// also reports messages from vkCreateInstance and vkDestroyInstance
+			ci = ci.push_next(&mut messenger_ci);
+		}
+		let vk = unsafe { vulkan.vk.create_instance(&ci, None) }.map_err(|err| match err {
+			InstanceError::LoadError(names) => Error::MissingExtensions(names.into_iter().map(String::from).collect()),
+			InstanceError::VkError(err) => err.into(),
+		})?;
+		let enabled = |name: &CStr| extensions.iter().any(|x| x.as_c_str() == name);
 		let khr_surface = khr::Surface::new(&vulkan.vk, &vk);
 		#[cfg(windows)]
-		let khr_win32_surface = khr::Win32Surface::new(&vulkan.vk, &vk);
+		let khr_win32_surface =
+			if enabled(khr::Win32Surface::name()) { Some(khr::Win32Surface::new(&vulkan.vk, &vk)) } else { None };
 		#[cfg(unix)]
-		let khr_xlib_surface = khr::XlibSurface::new(&vulkan.vk, &vk);
+		let khr_xlib_surface =
+			if enabled(khr::XlibSurface::name()) { Some(khr::XlibSurface::new(&vulkan.vk, &vk)) } else { None };
 		#[cfg(unix)]
-		let khr_wayland_surface = khr::WaylandSurface::new(&vulkan.vk, &vk);
+		let khr_wayland_surface =
+			if enabled(khr::WaylandSurface::name()) { Some(khr::WaylandSurface::new(&vulkan.vk, &vk)) } else { None };
+
+		let ext_headless_surface = if enabled(vk::ExtHeadlessSurfaceFn::name()) {
+			Some(vk::ExtHeadlessSurfaceFn::load(|name| unsafe {
+				mem::transmute(vulkan.vk.get_instance_proc_addr(vk.handle(), name.as_ptr()))
+			}))
+		} else {
+			None
+		};
+
+		let (ext_debug_utils, debug_messenger) = if debug_utils {
+			let ext_debug_utils = ext::DebugUtils::new(&vulkan.vk, &vk);
+			let messenger = unsafe { ext_debug_utils.create_debug_utils_messenger(&messenger_ci, None) };
+			let messenger = messenger.inspect_err(|_| unsafe { vk.destroy_instance(None) })?;
+			(Some(ext_debug_utils), messenger)
+		} else {
+			(None, vk::DebugUtilsMessengerEXT::null())
+		};
 
-		Arc::new(Self {
+		Ok(Arc::new(Instance {
 			_vulkan: vulkan,
 			vk,
 			khr_surface,
@@ -71,17 +238,79 @@ impl Instance {
 			khr_xlib_surface,
 			#[cfg(unix)]
 			khr_wayland_surface,
-		})
+			ext_debug_utils,
+			ext_headless_surface,
+			debug_messenger,
+			_debug_callback: debug_callback,
+			api_version: self.api_version,
+			extensions,
+			layers,
+		}))
 	}
-}
-impl Drop for Instance {
-	fn drop(&mut self) {
-		unsafe {
-			self.vk.destroy_instance(None);
-		}
+
+	/// Defaults to 1.0. Fails to build if the loader does not support the requested version.
+	pub fn api_version(mut self, api_version: Version) -> Self {
+		self.api_version = api_version;
+		self
+	}
+
+	/// Called for every message received by the debug messenger, after it has been logged.
+	pub fn debug_callback(
+		mut self,
+		callback: impl Fn(DebugMessageSeverity, DebugMessageType, &str) + Send + Sync + 'static,
+	) -> Self {
+		self.debug_callback = Some(Box::new(callback));
+		self
+	}
+
+	pub fn engine(mut self, engine_name: &'a str, engine_version: Version) -> Self {
+		self.engine_name = Some(engine_name);
+		self.engine_version = engine_version;
+		self
+	}
+
+	/// Requests an extension that must be available for the instance to be built.
+	pub fn extension(mut self, name: &CStr) -> Self {
+		self.extensions.push((name.to_owned(), true));
+		self
+	}
+
+	/// Skips the window system surface extensions so the instance can be created without a display server.
+	///
+	/// `VK_EXT_headless_surface` is enabled where available, see `Surface::headless`.
+	pub fn headless(mut self, headless: bool) -> Self {
+		self.headless = headless;
+		self
+	}
+
+	/// Requests a layer that must be available for the instance to be built.
+	pub fn layer(mut self, name: &CStr) -> Self {
+		self.layers.push((name.to_owned(), true));
+		self
+	}
+
+	/// Requests an extension that is enabled only if available. Check `Instance::is_extension_enabled` afterwards.
+	pub fn optional_extension(mut self, name: &CStr) -> Self {
+		self.extensions.push((name.to_owned(), false));
+		self
+	}
+
+	/// Requests a layer that is enabled only if available. Check `Instance::is_layer_enabled` afterwards.
+	pub fn optional_layer(mut self, name: &CStr) -> Self {
+		self.layers.push((name.to_owned(), false));
+		self
+	}
+
+	/// Enables `VK_LAYER_KHRONOS_validation` and logs its messages through the `log` crate.
+	///
+	/// Both the layer and `VK_EXT_debug_utils` are optional, so this is a no-op where they are not installed.
+	pub fn validation(mut self, validation: bool) -> Self {
+		self.validation = validation;
+		self
 	}
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Version {
 	vk: u32,
 }
@@ -89,4 +318,81 @@ impl Version {
 	pub fn new(major: u32, minor: u32, patch: u32) -> Self {
 		Self { vk: vk::make_version(major, minor, patch) }
 	}
+
+	pub fn major(self) -> u32 {
+		vk::version_major(self.vk)
+	}
+
+	pub fn minor(self) -> u32 {
+		vk::version_minor(self.vk)
+	}
+
+	pub fn patch(self) -> u32 {
+		vk::version_patch(self.vk)
+	}
+
+	pub(crate) fn from_vk(vk: u32) -> Self {
+		Self { vk }
+	}
+}
+impl fmt::Display for Version {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}.{}.{}", self.major(), self.minor(), self.patch())
+	}
+}
+
+/// Splits `requested` into the names that should be enabled, or returns the missing required names.
+pub(crate) fn negotiate(
+	kind: &str,
+	available: &HashSet<&CStr>,
+	requested: Vec<(CString, bool)>,
+) -> result::Result<Vec<CString>, Vec<String>> {
+	let mut enabled: Vec<CString> = vec![];
+	let mut missing = vec![];
+	for (name, required) in requested {
+		if enabled.contains(&name) {
+			continue;
+		} else if available.contains(name.as_c_str()) {
+			enabled.push(name);
+		} else if required {
+			let name = name.to_string_lossy().into_owned();
+			if !missing.contains(&name) {
+				missing.push(name);
+			}
+		} else {
+			debug!("optional {} {:?} is not available", kind, name);
+		}
+	}
+
+	if missing.is_empty() { Ok(enabled) } else { Err(missing) }
+}
+
+unsafe extern "system" fn debug_utils_callback(
+	severity: DebugMessageSeverity,
+	types: DebugMessageType,
+	data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+	user_data: *mut c_void,
+) -> vk::Bool32 {
+	let message = (*data).p_message;
+	let message = if message.is_null() { "".into() } else { CStr::from_ptr(message).to_string_lossy() };
+
+	let level = if severity.contains(DebugMessageSeverity::ERROR) {
+		Level::Error
+	} else if severity.contains(DebugMessageSeverity::WARNING) {
+		Level::Warn
+	} else if severity.contains(DebugMessageSeverity::INFO) {
+		Level::Info
+	} else {
+		Level::Trace
+	};
+	log!(level, "{:?}: {}", types, message);
+
+	if let Some(callback) = (user_data as *const DebugCallback).as_ref() {
+		// unwinding out of an `extern "system"` fn is undefined behavior
+		if panic::catch_unwind(AssertUnwindSafe(|| callback(severity, types, &message))).is_err() {
+			error!("debug messenger callback panicked");
+		}
+	}
+
+	vk::FALSE
 }
@@ -1,31 +1,96 @@
+#![allow(clippy::len_without_is_empty, clippy::missing_safety_doc, clippy::too_many_arguments)]
+
 pub mod buffer;
 pub mod command;
 pub mod descriptor;
 pub mod device;
+pub mod error;
 pub mod image;
 pub mod instance;
 pub mod physical_device;
 pub mod pipeline;
 pub mod render_pass;
+pub mod ring;
 pub mod shader;
+pub mod staging;
 pub mod surface;
 pub mod swapchain;
 pub mod sync;
 
-pub use ash::{
-	vk::{Extent2D, Offset2D, Rect2D, Result as VkResult},
-	LoadingError,
-};
+pub use crate::error::Error;
+pub use ash::vk::{Extent2D, Extent3D, Offset2D, Rect2D, Result as VkResult};
 
 use crate::instance::Instance;
 use ash::Entry;
-use std::sync::Arc;
+use libloading::Library;
+use std::{
+	error::Error as StdError,
+	ffi::c_void,
+	fmt,
+	path::{Path, PathBuf},
+	ptr,
+	sync::Arc,
+};
+
+#[cfg(windows)]
+const LIB_PATH: &str = "vulkan-1.dll";
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "ios", target_os = "android"))))]
+const LIB_PATH: &str = "libvulkan.so.1";
+#[cfg(target_os = "android")]
+const LIB_PATH: &str = "libvulkan.so";
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const LIB_PATH: &str = "libvulkan.dylib";
 
 pub struct Vulkan {
 	pub vk: Entry,
 }
 impl Vulkan {
+	/// Loads the system Vulkan loader.
 	pub fn new() -> Result<Arc<Self>, LoadingError> {
-		Ok(Arc::new(Self { vk: Entry::new()? }))
+		Self::with_library(LIB_PATH)
+	}
+
+	/// Loads the Vulkan loader from `library`, or an ICD directly if it exports `vk_icdGetInstanceProcAddr` instead of
+	/// `vkGetInstanceProcAddr`. To use the driver described by an ICD manifest, pass its `library_path`.
+	pub fn with_library(library: impl AsRef<Path>) -> Result<Arc<Self>, LoadingError> {
+		let path = library.as_ref();
+		let lib = Library::new(path).map_err(|source| LoadingError::Library { path: path.to_owned(), source })?;
+		let has_symbol = |symbol: &[u8]| unsafe { lib.get::<*const c_void>(symbol) }.is_ok();
+		let get_instance_proc_addr: &[u8] = if has_symbol(b"vkGetInstanceProcAddr\0") {
+			b"vkGetInstanceProcAddr\0"
+		} else if has_symbol(b"vk_icdGetInstanceProcAddr\0") {
+			b"vk_icdGetInstanceProcAddr\0"
+		} else {
+			return Err(LoadingError::MissingSymbol { path: path.to_owned(), symbol: "vkGetInstanceProcAddr" });
+		};
+
+		let vk = Entry::new_custom(Arc::new(lib), |lib, name| unsafe {
+			let name = name.to_bytes_with_nul();
+			let name = if name == b"vkGetInstanceProcAddr\0" { get_instance_proc_addr } else { name };
+			lib.get(name).map(|symbol| *symbol).unwrap_or(ptr::null_mut())
+		});
+		Ok(Arc::new(Self { vk }))
+	}
+}
+
+#[derive(Debug)]
+pub enum LoadingError {
+	Library { path: PathBuf, source: libloading::Error },
+	MissingSymbol { path: PathBuf, symbol: &'static str },
+}
+impl fmt::Display for LoadingError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Library { path, source } => write!(f, "failed to load {}: {}", path.display(), source),
+			Self::MissingSymbol { path, symbol } => write!(f, "{} does not export {}", path.display(), symbol),
+		}
+	}
+}
+impl StdError for LoadingError {
+	fn source(&self) -> Option<&(dyn StdError + 'static)> {
+		match self {
+			Self::Library { source, .. } => Some(source),
+			Self::MissingSymbol { .. } => None,
+		}
 	}
 }
@@ -1,9 +1,81 @@
+pub use ash::vk::{
+	DriverId, FormatFeatureFlags, FormatProperties, ImageFormatProperties, ImageTiling, PhysicalDeviceLimits,
+	PhysicalDeviceSparseProperties, PhysicalDeviceType, PhysicalDeviceVulkan11Properties,
+	PhysicalDeviceVulkan12Properties,
+};
+
 use crate::{
-	instance::Instance,
+	error::{Error, Result},
+	image::{Format, ImageCreateFlags, ImageType, ImageUsageFlags},
+	instance::{Instance, Version},
 	surface::{PresentMode, Surface, SurfaceCapabilities, SurfaceFormat},
+	Extent3D,
+};
+use ash::{
+	extensions::khr,
+	version::{InstanceV1_0, InstanceV1_1},
+	vk,
+};
+use std::{
+	borrow::Cow,
+	ffi::{CStr, CString},
+	ptr, result,
+	sync::Arc,
 };
-use ash::{version::InstanceV1_0, vk};
-use std::sync::Arc;
+
+/// Invokes `$callback!(struct, field)` for every feature in `Features`.
+macro_rules! for_each_feature {
+	($callback:ident) => {
+		for_each_feature!(@fields $callback, core, [
+			robust_buffer_access, full_draw_index_uint32, image_cube_array, independent_blend, geometry_shader,
+			tessellation_shader, sample_rate_shading, dual_src_blend, logic_op, multi_draw_indirect,
+			draw_indirect_first_instance, depth_clamp, depth_bias_clamp, fill_mode_non_solid, depth_bounds, wide_lines,
+			large_points, alpha_to_one, multi_viewport, sampler_anisotropy, texture_compression_etc2,
+			texture_compression_astc_ldr, texture_compression_bc, occlusion_query_precise, pipeline_statistics_query,
+			vertex_pipeline_stores_and_atomics, fragment_stores_and_atomics,
+			shader_tessellation_and_geometry_point_size, shader_image_gather_extended,
+			shader_storage_image_extended_formats, shader_storage_image_multisample,
+			shader_storage_image_read_without_format, shader_storage_image_write_without_format,
+			shader_uniform_buffer_array_dynamic_indexing, shader_sampled_image_array_dynamic_indexing,
+			shader_storage_buffer_array_dynamic_indexing, shader_storage_image_array_dynamic_indexing,
+			shader_clip_distance, shader_cull_distance, shader_float64, shader_int64, shader_int16,
+			shader_resource_residency, shader_resource_min_lod, sparse_binding, sparse_residency_buffer,
+			sparse_residency_image2_d, sparse_residency_image3_d, sparse_residency2_samples, sparse_residency4_samples,
+			sparse_residency8_samples, sparse_residency16_samples, sparse_residency_aliased, variable_multisample_rate,
+			inherited_queries,
+		]);
+		for_each_feature!(@fields $callback, vulkan11, [
+			storage_buffer16_bit_access, uniform_and_storage_buffer16_bit_access, storage_push_constant16,
+			storage_input_output16, multiview, multiview_geometry_shader, multiview_tessellation_shader,
+			variable_pointers_storage_buffer, variable_pointers, protected_memory, sampler_ycbcr_conversion,
+			shader_draw_parameters,
+		]);
+		for_each_feature!(@fields $callback, vulkan12, [
+			sampler_mirror_clamp_to_edge, draw_indirect_count, storage_buffer8_bit_access,
+			uniform_and_storage_buffer8_bit_access, storage_push_constant8, shader_buffer_int64_atomics,
+			shader_shared_int64_atomics, shader_float16, shader_int8, descriptor_indexing,
+			shader_input_attachment_array_dynamic_indexing, shader_uniform_texel_buffer_array_dynamic_indexing,
+			shader_storage_texel_buffer_array_dynamic_indexing, shader_uniform_buffer_array_non_uniform_indexing,
+			shader_sampled_image_array_non_uniform_indexing, shader_storage_buffer_array_non_uniform_indexing,
+			shader_storage_image_array_non_uniform_indexing, shader_input_attachment_array_non_uniform_indexing,
+			shader_uniform_texel_buffer_array_non_uniform_indexing,
+			shader_storage_texel_buffer_array_non_uniform_indexing, descriptor_binding_uniform_buffer_update_after_bind,
+			descriptor_binding_sampled_image_update_after_bind, descriptor_binding_storage_image_update_after_bind,
+			descriptor_binding_storage_buffer_update_after_bind,
+			descriptor_binding_uniform_texel_buffer_update_after_bind,
+			descriptor_binding_storage_texel_buffer_update_after_bind, descriptor_binding_update_unused_while_pending,
+			descriptor_binding_partially_bound, descriptor_binding_variable_descriptor_count, runtime_descriptor_array,
+			sampler_filter_minmax, scalar_block_layout, imageless_framebuffer, uniform_buffer_standard_layout,
+			shader_subgroup_extended_types, separate_depth_stencil_layouts, host_query_reset, timeline_semaphore,
+			buffer_device_address, buffer_device_address_capture_replay, buffer_device_address_multi_device,
+			vulkan_memory_model, vulkan_memory_model_device_scope, vulkan_memory_model_availability_visibility_chains,
+			shader_output_viewport_index, shader_output_layer, subgroup_broadcast_dynamic_id,
+		]);
+	};
+	(@fields $callback:ident, $struct:ident, [$($field:ident),* $(,)?]) => {
+		$($callback!($struct, $field);)*
+	};
+}
 
 #[derive(Clone)]
 pub struct PhysicalDevice {
@@ -11,11 +83,56 @@ pub struct PhysicalDevice {
 	pub vk: vk::PhysicalDevice,
 }
 impl PhysicalDevice {
-	pub fn enumerate<'a>(instance: &'a Arc<Instance>) -> impl Iterator<Item = Arc<Self>> + 'a {
-		unsafe { instance.vk.enumerate_physical_devices() }
-			.unwrap()
-			.into_iter()
-			.map(move |vk| Arc::new(Self { instance: instance.clone(), vk }))
+	pub fn enumerate<'a>(instance: &'a Arc<Instance>) -> Result<impl Iterator<Item = Arc<Self>> + 'a> {
+		let vks = unsafe { instance.vk.enumerate_physical_devices() }?;
+		Ok(vks.into_iter().map(move |vk| Arc::new(Self { instance: instance.clone(), vk })))
+	}
+
+	/// Core features, plus the 1.1 and 1.2 feature structs when both the instance and device support Vulkan 1.2.
+	pub fn features(&self) -> Features {
+		let mut features = Features::default();
+		if self.api_version() >= Version::new(1, 2, 0) {
+			// PhysicalDeviceFeatures2 has no push_next in this version of ash
+			features.vulkan11.p_next = &mut features.vulkan12 as *mut _ as _;
+			let mut features2 = vk::PhysicalDeviceFeatures2 {
+				p_next: &mut features.vulkan11 as *mut _ as _,
+				..Default::default()
+			};
+			unsafe { self.instance.vk.get_physical_device_features2(self.vk, &mut features2) };
+			features.core = features2.features;
+		} else {
+			features.core = unsafe { self.instance.vk.get_physical_device_features(self.vk) };
+		}
+		features.vulkan11.p_next = ptr::null_mut();
+		features.vulkan12.p_next = ptr::null_mut();
+		features
+	}
+
+	pub fn get_extension_properties(&self) -> Result<Vec<vk::ExtensionProperties>> {
+		Ok(unsafe { self.instance.vk.enumerate_device_extension_properties(self.vk) }?)
+	}
+
+	pub fn get_format_properties(&self, format: Format) -> FormatProperties {
+		unsafe { self.instance.vk.get_physical_device_format_properties(self.vk, format) }
+	}
+
+	/// Fails with `Error::UnsupportedFormat` if the combination of parameters isn't supported for `format`.
+	pub fn get_image_format_properties(
+		&self,
+		format: Format,
+		image_type: ImageType,
+		tiling: ImageTiling,
+		usage: ImageUsageFlags,
+		flags: ImageCreateFlags,
+	) -> Result<ImageFormatProperties> {
+		let instance = &self.instance.vk;
+		let res = unsafe {
+			instance.get_physical_device_image_format_properties(self.vk, format, image_type, tiling, usage, flags)
+		};
+		match res {
+			Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED) => Err(Error::UnsupportedFormat(format)),
+			res => Ok(res?),
+		}
 	}
 
 	pub fn get_queue_family_properties<'a>(
@@ -26,25 +143,98 @@ impl PhysicalDevice {
 		)
 	}
 
-	pub fn get_surface_capabilities<T>(&self, surface: &Surface<T>) -> SurfaceCapabilities {
-		unsafe { self.instance.khr_surface.get_physical_device_surface_capabilities(self.vk, surface.vk) }.unwrap()
+	pub fn get_surface_capabilities<T>(&self, surface: &Surface<T>) -> Result<SurfaceCapabilities> {
+		Ok(unsafe { self.instance.khr_surface.get_physical_device_surface_capabilities(self.vk, surface.vk) }?)
 	}
 
-	pub fn get_surface_formats<T>(&self, surface: &Surface<T>) -> Vec<SurfaceFormat> {
-		unsafe { self.instance.khr_surface.get_physical_device_surface_formats(self.vk, surface.vk) }.unwrap()
+	pub fn get_surface_formats<T>(&self, surface: &Surface<T>) -> Result<Vec<SurfaceFormat>> {
+		Ok(unsafe { self.instance.khr_surface.get_physical_device_surface_formats(self.vk, surface.vk) }?)
 	}
 
-	pub fn get_surface_present_modes<T>(&self, surface: &Surface<T>) -> Vec<PresentMode> {
-		unsafe { self.instance.khr_surface.get_physical_device_surface_present_modes(self.vk, surface.vk) }.unwrap()
+	pub fn get_surface_present_modes<T>(&self, surface: &Surface<T>) -> Result<Vec<PresentMode>> {
+		Ok(unsafe { self.instance.khr_surface.get_physical_device_surface_present_modes(self.vk, surface.vk) }?)
 	}
 
-	pub fn get_surface_support<T>(&self, qfam: &QueueFamily, surface: &Surface<T>) -> bool {
-		unsafe { self.instance.khr_surface.get_physical_device_surface_support(self.vk, qfam.idx, surface.vk) }.unwrap()
+	pub fn get_surface_support<T>(&self, qfam: &QueueFamily, surface: &Surface<T>) -> Result<bool> {
+		Ok(unsafe { self.instance.khr_surface.get_physical_device_surface_support(self.vk, qfam.idx, surface.vk) }?)
 	}
 
 	pub fn instance(&self) -> &Arc<Instance> {
 		&self.instance
 	}
+
+	pub fn selector(instance: &Arc<Instance>) -> PhysicalDeviceSelector<'_> {
+		PhysicalDeviceSelector { instance, extensions: vec![], features: Features::default(), surface: None }
+	}
+
+	/// Core properties, plus the 1.1 and 1.2 property structs when both the instance and device support them.
+	pub fn properties(&self) -> PhysicalDeviceProperties {
+		let api_version = self.api_version();
+		let mut props = PhysicalDeviceProperties {
+			vk: unsafe { self.instance.vk.get_physical_device_properties(self.vk) },
+			id: None,
+			vulkan11: None,
+			vulkan12: None,
+		};
+
+		if api_version >= Version::new(1, 2, 0) {
+			let mut vulkan11 = vk::PhysicalDeviceVulkan11Properties::default();
+			let mut vulkan12 = vk::PhysicalDeviceVulkan12Properties::default();
+			let mut props2 = vk::PhysicalDeviceProperties2::builder().push_next(&mut vulkan11).push_next(&mut vulkan12);
+			unsafe { self.instance.vk.get_physical_device_properties2(self.vk, &mut props2) };
+			vulkan11.p_next = ptr::null_mut();
+			vulkan12.p_next = ptr::null_mut();
+			props.vulkan11 = Some(vulkan11);
+			props.vulkan12 = Some(vulkan12);
+		} else if api_version >= Version::new(1, 1, 0) {
+			let mut id = vk::PhysicalDeviceIDProperties::default();
+			let mut props2 = vk::PhysicalDeviceProperties2::builder().push_next(&mut id);
+			unsafe { self.instance.vk.get_physical_device_properties2(self.vk, &mut props2) };
+			id.p_next = ptr::null_mut();
+			props.id = Some(id);
+		}
+
+		props
+	}
+
+	pub fn supports_extension(&self, name: &CStr) -> Result<bool> {
+		let props = self.get_extension_properties()?;
+		Ok(props.iter().any(|props| unsafe { CStr::from_ptr(props.extension_name.as_ptr()) } == name))
+	}
+
+	/// The lower of the instance's requested API version and the device's supported API version.
+	pub(crate) fn api_version(&self) -> Version {
+		let props = unsafe { self.instance.vk.get_physical_device_properties(self.vk) };
+		self.instance.api_version().min(Version::from_vk(props.api_version))
+	}
+
+	/// The first graphics family, limited to those that can present to `surface` if given, and the first compute
+	/// family without graphics support and transfer family without graphics or compute support. Shared by
+	/// `PhysicalDeviceSelector` and `DeviceBuilder` so both pick the same families.
+	pub(crate) fn find_queue_families(self: &Arc<Self>, surface: Option<vk::SurfaceKHR>) -> Result<QueueFamilyIndices> {
+		let mut families = QueueFamilyIndices { graphics: None, compute: None, transfer: None };
+		for props in self.get_queue_family_properties() {
+			let flags = props.queue_flags();
+			let idx = props.family.idx;
+			if flags.graphics() {
+				if families.graphics.is_some() {
+					continue;
+				}
+				if let Some(surface) = surface {
+					let khr_surface = &self.instance.khr_surface;
+					if !unsafe { khr_surface.get_physical_device_surface_support(self.vk, idx, surface) }? {
+						continue;
+					}
+				}
+				families.graphics = Some(idx);
+			} else if flags.compute() {
+				families.compute = families.compute.or(Some(idx));
+			} else if flags.transfer() {
+				families.transfer = families.transfer.or(Some(idx));
+			}
+		}
+		Ok(families)
+	}
 }
 impl PartialEq for PhysicalDevice {
 	fn eq(&self, other: &PhysicalDevice) -> bool {
@@ -53,6 +243,276 @@ impl PartialEq for PhysicalDevice {
 }
 impl Eq for PhysicalDevice {}
 
+/// Picks the most capable physical device that meets a set of requirements.
+pub struct PhysicalDeviceSelector<'a> {
+	instance: &'a Arc<Instance>,
+	extensions: Vec<CString>,
+	features: Features,
+	surface: Option<vk::SurfaceKHR>,
+}
+impl<'a> PhysicalDeviceSelector<'a> {
+	/// Prefers discrete, then integrated, then virtual, then CPU devices. Returns
+	/// `Error::NoSuitablePhysicalDevice` listing why each device was rejected if none qualify.
+	pub fn select(self) -> Result<SelectedPhysicalDevice> {
+		let mut best: Option<(u32, SelectedPhysicalDevice)> = None;
+		let mut rejected = vec![];
+		for physical_device in PhysicalDevice::enumerate(self.instance)? {
+			let props = physical_device.properties();
+			match self.check(physical_device)? {
+				Ok(selected) => {
+					let score = match props.device_type() {
+						PhysicalDeviceType::DISCRETE_GPU => 4,
+						PhysicalDeviceType::INTEGRATED_GPU => 3,
+						PhysicalDeviceType::VIRTUAL_GPU => 2,
+						PhysicalDeviceType::CPU => 1,
+						_ => 0,
+					};
+					if best.as_ref().is_none_or(|(best, _)| score > *best) {
+						best = Some((score, selected));
+					}
+				},
+				Err(reasons) => rejected.push((props.device_name().into_owned(), reasons)),
+			}
+		}
+		best.map(|(_, selected)| selected).ok_or(Error::NoSuitablePhysicalDevice(rejected))
+	}
+
+	pub fn extension(mut self, name: &CStr) -> Self {
+		self.extensions.push(name.to_owned());
+		self
+	}
+
+	pub fn features(mut self, features: Features) -> Self {
+		self.features = features;
+		self
+	}
+
+	/// Requires a graphics queue family that can present to `surface`, and `VK_KHR_swapchain`.
+	pub fn surface<T>(mut self, surface: &'a Surface<T>) -> Self {
+		self.surface = Some(surface.vk);
+		self
+	}
+
+	fn check(
+		&self,
+		physical_device: Arc<PhysicalDevice>,
+	) -> Result<result::Result<SelectedPhysicalDevice, Vec<String>>> {
+		let mut reasons = vec![];
+
+		let available = physical_device.get_extension_properties()?;
+		let available = available.iter().map(|props| unsafe { CStr::from_ptr(props.extension_name.as_ptr()) });
+		let available = available.collect::<Vec<_>>();
+		let swapchain = self.surface.map(|_| khr::Swapchain::name().to_owned());
+		let missing = self
+			.extensions
+			.iter()
+			.chain(&swapchain)
+			.filter(|name| !available.contains(&name.as_c_str()))
+			.map(|name| name.to_string_lossy())
+			.collect::<Vec<_>>();
+		if !missing.is_empty() {
+			reasons.push(format!("missing extensions {}", missing.join(", ")));
+		}
+
+		let missing = self.features.missing_from(&physical_device.features());
+		if !missing.is_empty() {
+			reasons.push(format!("missing features {}", missing.join(", ")));
+		}
+
+		if let Some(surface) = self.surface {
+			let khr_surface = &self.instance.khr_surface;
+			if unsafe { khr_surface.get_physical_device_surface_formats(physical_device.vk, surface) }?.is_empty() {
+				reasons.push("no supported surface formats".to_owned());
+			}
+		}
+
+		let families = physical_device.find_queue_families(self.surface)?;
+		if families.graphics.is_none() {
+			match self.surface {
+				Some(_) => reasons.push("no graphics queue family that can present to the surface".to_owned()),
+				None => reasons.push("no graphics queue family".to_owned()),
+			}
+		}
+
+		if !reasons.is_empty() {
+			return Ok(Err(reasons));
+		}
+		let family = |idx| unsafe { QueueFamily::from_vk(physical_device.clone(), idx) };
+		let (graphics, compute, transfer) =
+			(family(families.graphics.unwrap()), families.compute.map(family), families.transfer.map(family));
+		Ok(Ok(SelectedPhysicalDevice { physical_device, graphics, compute, transfer }))
+	}
+}
+
+pub struct SelectedPhysicalDevice {
+	pub physical_device: Arc<PhysicalDevice>,
+	/// Supports presentation if a surface was given to the selector.
+	pub graphics: QueueFamily,
+	/// A compute family without graphics support, typically backed by async compute hardware.
+	pub compute: Option<QueueFamily>,
+	/// A transfer family without graphics or compute support, typically backed by a DMA engine.
+	pub transfer: Option<QueueFamily>,
+}
+
+/// Family indices found by `PhysicalDevice::find_queue_families`.
+#[derive(Clone, Copy)]
+pub(crate) struct QueueFamilyIndices {
+	pub graphics: Option<u32>,
+	pub compute: Option<u32>,
+	pub transfer: Option<u32>,
+}
+
+pub struct PhysicalDeviceProperties {
+	vk: vk::PhysicalDeviceProperties,
+	id: Option<vk::PhysicalDeviceIDProperties>,
+	vulkan11: Option<PhysicalDeviceVulkan11Properties>,
+	vulkan12: Option<PhysicalDeviceVulkan12Properties>,
+}
+impl PhysicalDeviceProperties {
+	pub fn api_version(&self) -> Version {
+		Version::from_vk(self.vk.api_version)
+	}
+
+	pub fn device_id(&self) -> u32 {
+		self.vk.device_id
+	}
+
+	pub fn device_name(&self) -> Cow<'_, str> {
+		unsafe { CStr::from_ptr(self.vk.device_name.as_ptr()) }.to_string_lossy()
+	}
+
+	pub fn device_type(&self) -> PhysicalDeviceType {
+		self.vk.device_type
+	}
+
+	/// Requires Vulkan 1.1.
+	pub fn device_uuid(&self) -> Option<[u8; vk::UUID_SIZE]> {
+		self.vulkan11.map(|x| x.device_uuid).or_else(|| self.id.map(|x| x.device_uuid))
+	}
+
+	/// Requires Vulkan 1.2.
+	pub fn driver_id(&self) -> Option<DriverId> {
+		self.vulkan12.map(|x| x.driver_id)
+	}
+
+	/// Requires Vulkan 1.2.
+	pub fn driver_name(&self) -> Option<Cow<'_, str>> {
+		self.vulkan12.as_ref().map(|x| unsafe { CStr::from_ptr(x.driver_name.as_ptr()) }.to_string_lossy())
+	}
+
+	/// Requires Vulkan 1.1.
+	pub fn driver_uuid(&self) -> Option<[u8; vk::UUID_SIZE]> {
+		self.vulkan11.map(|x| x.driver_uuid).or_else(|| self.id.map(|x| x.driver_uuid))
+	}
+
+	/// Vendor-specific encoding.
+	pub fn driver_version(&self) -> u32 {
+		self.vk.driver_version
+	}
+
+	pub fn limits(&self) -> &PhysicalDeviceLimits {
+		&self.vk.limits
+	}
+
+	pub fn pipeline_cache_uuid(&self) -> [u8; vk::UUID_SIZE] {
+		self.vk.pipeline_cache_uuid
+	}
+
+	pub fn sparse_properties(&self) -> &PhysicalDeviceSparseProperties {
+		&self.vk.sparse_properties
+	}
+
+	pub fn vendor_id(&self) -> u32 {
+		self.vk.vendor_id
+	}
+
+	pub fn vulkan11(&self) -> Option<&PhysicalDeviceVulkan11Properties> {
+		self.vulkan11.as_ref()
+	}
+
+	pub fn vulkan12(&self) -> Option<&PhysicalDeviceVulkan12Properties> {
+		self.vulkan12.as_ref()
+	}
+}
+// p_next is always null, as the fields are private and nulled in `PhysicalDevice::properties`
+unsafe impl Send for PhysicalDeviceProperties {}
+unsafe impl Sync for PhysicalDeviceProperties {}
+
+/// Feature structs are left zeroed (all unsupported) when the device or instance is older than Vulkan 1.2.
+///
+/// The structs' `p_next` is always null. Chain extension feature structs with `DeviceBuilder::push_next` instead.
+#[derive(Clone, Copy, Default)]
+pub struct Features {
+	core: vk::PhysicalDeviceFeatures,
+	vulkan11: vk::PhysicalDeviceVulkan11Features,
+	vulkan12: vk::PhysicalDeviceVulkan12Features,
+}
+impl Features {
+	pub fn core(&self) -> &vk::PhysicalDeviceFeatures {
+		&self.core
+	}
+
+	pub fn core_mut(&mut self) -> &mut vk::PhysicalDeviceFeatures {
+		&mut self.core
+	}
+
+	/// Features enabled in both `self` and `other`.
+	pub fn intersection(&self, other: &Features) -> Features {
+		self.zip(other, |a, b| a != vk::FALSE && b != vk::FALSE)
+	}
+
+	/// Names of the features enabled in `self` but not in `supported`.
+	pub fn missing_from(&self, supported: &Features) -> Vec<&'static str> {
+		let mut missing = vec![];
+		macro_rules! check {
+			($struct:ident, $field:ident) => {
+				if self.$struct.$field != vk::FALSE && supported.$struct.$field == vk::FALSE {
+					missing.push(stringify!($field));
+				}
+			};
+		}
+		for_each_feature!(check);
+		missing
+	}
+
+	/// `p_next` is ignored.
+	pub fn set_vulkan11(&mut self, vulkan11: vk::PhysicalDeviceVulkan11Features) {
+		self.vulkan11 = vk::PhysicalDeviceVulkan11Features { p_next: ptr::null_mut(), ..vulkan11 };
+	}
+
+	/// `p_next` is ignored.
+	pub fn set_vulkan12(&mut self, vulkan12: vk::PhysicalDeviceVulkan12Features) {
+		self.vulkan12 = vk::PhysicalDeviceVulkan12Features { p_next: ptr::null_mut(), ..vulkan12 };
+	}
+
+	/// Features enabled in either `self` or `other`.
+	pub fn union(&self, other: &Features) -> Features {
+		self.zip(other, |a, b| a != vk::FALSE || b != vk::FALSE)
+	}
+
+	pub fn vulkan11(&self) -> &vk::PhysicalDeviceVulkan11Features {
+		&self.vulkan11
+	}
+
+	pub fn vulkan12(&self) -> &vk::PhysicalDeviceVulkan12Features {
+		&self.vulkan12
+	}
+
+	fn zip(&self, other: &Features, f: impl Fn(vk::Bool32, vk::Bool32) -> bool) -> Features {
+		let mut features = Features::default();
+		macro_rules! zip {
+			($struct:ident, $field:ident) => {
+				features.$struct.$field = f(self.$struct.$field, other.$struct.$field) as vk::Bool32;
+			};
+		}
+		for_each_feature!(zip);
+		features
+	}
+}
+// p_next is always null, as the fields are private and only set through `set_vulkan11` and `set_vulkan12`
+unsafe impl Send for Features {}
+unsafe impl Sync for Features {}
+
 pub struct QueueFamilyProperties {
 	family: QueueFamily,
 	vk: vk::QueueFamilyProperties,
@@ -62,9 +522,23 @@ impl QueueFamilyProperties {
 		self.family
 	}
 
+	/// Granularity of image transfer operations, in texels. Zero means only whole mip levels can be transferred.
+	pub fn min_image_transfer_granularity(&self) -> Extent3D {
+		self.vk.min_image_transfer_granularity
+	}
+
+	pub fn queue_count(&self) -> u32 {
+		self.vk.queue_count
+	}
+
 	pub fn queue_flags(&self) -> QueueFlags {
 		QueueFlags { vk: self.vk.queue_flags }
 	}
+
+	/// Zero if timestamps are not supported.
+	pub fn timestamp_valid_bits(&self) -> u32 {
+		self.vk.timestamp_valid_bits
+	}
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -87,7 +561,29 @@ pub struct QueueFlags {
 	vk: vk::QueueFlags,
 }
 impl QueueFlags {
+	pub fn compute(self) -> bool {
+		self.vk.contains(vk::QueueFlags::COMPUTE)
+	}
+
 	pub fn graphics(self) -> bool {
 		self.vk.contains(vk::QueueFlags::GRAPHICS)
 	}
+
+	pub fn protected(self) -> bool {
+		self.vk.contains(vk::QueueFlags::PROTECTED)
+	}
+
+	pub fn sparse_binding(self) -> bool {
+		self.vk.contains(vk::QueueFlags::SPARSE_BINDING)
+	}
+
+	/// Also true for graphics and compute families, which support transfers even if they don't report it.
+	pub fn supports_transfer(self) -> bool {
+		self.vk.intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER)
+	}
+
+	/// Whether the family reports `TRANSFER`. See `supports_transfer` for whether it can be used for transfers.
+	pub fn transfer(self) -> bool {
+		self.vk.contains(vk::QueueFlags::TRANSFER)
+	}
 }
@@ -3,6 +3,7 @@ pub use ash::vk::{VertexInputAttributeDescription, Viewport};
 use crate::{
 	descriptor::DescriptorSetLayout,
 	device::Device,
+	error::Result,
 	render_pass::RenderPass,
 	shader::{ShaderModule, ShaderStageFlags},
 	Extent2D, Offset2D,
@@ -25,7 +26,7 @@ impl PipelineLayout {
 		device: Arc<Device>,
 		set_layouts: Vec<Arc<DescriptorSetLayout>>,
 		push_constant_ranges: impl IntoIterator<Item = (ShaderStageFlags, u32, u32)>,
-	) -> Arc<PipelineLayout> {
+	) -> Result<Arc<PipelineLayout>> {
 		let set_layout_vks: Vec<_> = set_layouts.iter().map(|x| x.vk).collect();
 
 		let push_constant_ranges: Vec<_> = push_constant_ranges
@@ -39,8 +40,13 @@ impl PipelineLayout {
 			.set_layouts(&set_layout_vks)
 			.push_constant_ranges(&push_constant_ranges)
 			.build();
-		let vk = unsafe { device.vk.create_pipeline_layout(&ci, None) }.unwrap();
-		Arc::new(Self { device, vk, _set_layouts: set_layouts })
+		device.check_lost()?;
+		let vk = device.check(unsafe { device.vk.create_pipeline_layout(&ci, None) })?;
+		Ok(Arc::new(Self { device, vk, _set_layouts: set_layouts }))
+	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
 	}
 }
 impl Drop for PipelineLayout {
@@ -55,6 +61,11 @@ pub struct ComputePipeline {
 	_shader: Arc<ShaderModule>,
 	pub vk: vk::Pipeline,
 }
+impl ComputePipeline {
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
+	}
+}
 impl Drop for ComputePipeline {
 	fn drop(&mut self) {
 		unsafe { self.device.vk.destroy_pipeline(self.vk, None) };
@@ -73,6 +84,10 @@ impl GraphicsPipeline {
 	pub fn render_pass(&self) -> &Arc<RenderPass> {
 		&self.render_pass
 	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
+	}
 }
 impl Drop for GraphicsPipeline {
 	fn drop(&mut self) {
@@ -90,7 +105,7 @@ pub struct GraphicsPipelineBuilder<'a, T: VertexDesc> {
 	viewports: &'a [Viewport],
 }
 impl<'a, T: VertexDesc> GraphicsPipelineBuilder<'a, T> {
-	pub fn build(self) -> Arc<GraphicsPipeline> {
+	pub fn build(self) -> Result<Arc<GraphicsPipeline>> {
 		let mut stages = vec![
 			vk::PipelineShaderStageCreateInfo::builder()
 				.stage(vk::ShaderStageFlags::VERTEX)
@@ -152,16 +167,18 @@ impl<'a, T: VertexDesc> GraphicsPipelineBuilder<'a, T> {
 			.layout(self.layout.vk)
 			.render_pass(self.render_pass.vk)
 			.build()];
-		let vk = unsafe { self.device.vk.create_graphics_pipelines(vk::PipelineCache::null(), &cis, None) }.unwrap()[0];
+		self.device.check_lost()?;
+		let vks = unsafe { self.device.vk.create_graphics_pipelines(vk::PipelineCache::null(), &cis, None) };
+		let vk = self.device.check(vks.map_err(|(_, err)| err))?[0];
 
-		Arc::new(GraphicsPipeline {
+		Ok(Arc::new(GraphicsPipeline {
 			device: self.device,
 			_layout: self.layout,
 			render_pass: self.render_pass,
 			_vertex_shader: self.vertex_shader.unwrap(),
 			_fragment_shader: self.fragment_shader.unwrap(),
 			vk,
-		})
+		}))
 	}
 
 	pub fn vertex_shader(mut self, vertex_shader: Arc<ShaderModule>) -> Self {
@@ -203,17 +220,19 @@ pub struct ComputePipelineBuilder {
 	shader: Option<Arc<ShaderModule>>,
 }
 impl ComputePipelineBuilder {
-	pub fn build(self) -> Arc<ComputePipeline> {
+	pub fn build(self) -> Result<Arc<ComputePipeline>> {
 		let stage = vk::PipelineShaderStageCreateInfo::builder()
-			.stage(vk::ShaderStageFlags::VERTEX)
+			.stage(vk::ShaderStageFlags::COMPUTE)
 			.module(self.shader.as_ref().unwrap().vk)
 			.name(CStr::from_bytes_with_nul(b"main\0").unwrap())
 			.build();
 
 		let cis = [vk::ComputePipelineCreateInfo::builder().stage(stage).layout(self.layout.vk).build()];
-		let vk = unsafe { self.device.vk.create_compute_pipelines(vk::PipelineCache::null(), &cis, None) }.unwrap()[0];
+		self.device.check_lost()?;
+		let vks = unsafe { self.device.vk.create_compute_pipelines(vk::PipelineCache::null(), &cis, None) };
+		let vk = self.device.check(vks.map_err(|(_, err)| err))?[0];
 
-		Arc::new(ComputePipeline { device: self.device, _layout: self.layout, _shader: self.shader.unwrap(), vk })
+		Ok(Arc::new(ComputePipeline { device: self.device, _layout: self.layout, _shader: self.shader.unwrap(), vk }))
 	}
 
 	pub fn shader(mut self, shader: Arc<ShaderModule>) -> Self {
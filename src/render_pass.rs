@@ -3,7 +3,7 @@ pub use ash::vk::{
 	SampleCountFlags, SubpassDependency, SubpassDescription, SUBPASS_EXTERNAL,
 };
 
-use crate::device::Device;
+use crate::{device::Device, error::Result};
 use ash::{version::DeviceV1_0, vk};
 use std::sync::Arc;
 
@@ -17,19 +17,24 @@ impl RenderPass {
 		attachments: &[AttachmentDescription],
 		subpasses: &[SubpassDescription],
 		dependencies: &[SubpassDependency],
-	) -> Arc<Self> {
+	) -> Result<Arc<Self>> {
 		let ci = vk::RenderPassCreateInfo::builder()
-			.attachments(&attachments)
-			.subpasses(&subpasses)
-			.dependencies(&dependencies);
-		let vk = unsafe { device.vk.create_render_pass(&ci, None) }.unwrap();
-		Arc::new(Self { device: device.clone(), vk })
+			.attachments(attachments)
+			.subpasses(subpasses)
+			.dependencies(dependencies);
+		device.check_lost()?;
+		let vk = device.check(unsafe { device.vk.create_render_pass(&ci, None) })?;
+		Ok(Arc::new(Self { device: device.clone(), vk }))
 	}
 
 	pub fn device(&self) -> &Arc<Device> {
 		&self.device
 	}
 
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
+	}
+
 	pub unsafe fn from_vk(device: Arc<Device>, vk: vk::RenderPass) -> Arc<Self> {
 		Arc::new(Self { device, vk })
 	}
@@ -0,0 +1,95 @@
+use crate::{
+	buffer::{Buffer, BufferSlice, CpuToGpu, MemoryLocation, Pod},
+	device::Device,
+	error::{Error, Result},
+	sync::Fence,
+};
+use ash::vk;
+use std::{convert::TryFrom, mem::size_of, slice, sync::Arc};
+use vk::BufferUsageFlags;
+
+/// A host-visible uniform buffer split into one region per frame in flight, for per-draw data bound with dynamic
+/// offsets. A frame's region is reused only after the fence passed to `end_frame` for it has signalled.
+pub struct UniformRing {
+	buffer: Arc<Buffer<[u8]>>,
+	alignment: u64,
+	frame_size: u64,
+	// the descriptor range bound at each offset from `push`
+	range: u64,
+	fences: Vec<Option<Arc<Fence>>>,
+	frame: usize,
+	// bytes of the current frame's region handed out so far
+	used: u64,
+}
+impl UniformRing {
+	/// `range` is the size of the descriptor range bound at each offset, and so the largest type `push` accepts.
+	/// `frame_size` is rounded up to `minUniformBufferOffsetAlignment`. Fails with `Error::LimitExceeded` if `range` is
+	/// more than `maxUniformBufferRange`, or if the whole ring is too large for 32-bit dynamic offsets. Panics if
+	/// `range` is 0 or more than `frame_size`.
+	pub fn new(device: Arc<Device>, frame_size: u64, range: u64, frame_count: usize) -> Result<Self> {
+		assert!(frame_count > 0 && range > 0 && range <= frame_size);
+
+		let limits = *device.physical_device().properties().limits();
+		let alignment = limits.min_uniform_buffer_offset_alignment.max(1);
+		let frame_size = frame_size.div_ceil(alignment) * alignment;
+		let max = limits.max_uniform_buffer_range as u64;
+		if range > max {
+			return Err(Error::LimitExceeded { limit: "maxUniformBufferRange", value: range, max });
+		}
+		let size = frame_size * frame_count as u64;
+		if size > u32::MAX as u64 {
+			return Err(Error::LimitExceeded { limit: "dynamic offset range", value: size, max: u32::MAX as u64 });
+		}
+		let buffer = Buffer::new(device, size, BufferUsageFlags::UNIFORM_BUFFER, CpuToGpu::MEMORY_USAGE)?;
+		let fences = (0..frame_count).map(|_| None).collect();
+		Ok(Self { buffer, alignment, frame_size, range, fences, frame: frame_count - 1, used: 0 })
+	}
+
+	/// Moves to the next frame's region, blocking until the GPU is done with its previous contents.
+	pub fn begin_frame(&mut self) -> Result<()> {
+		self.frame = (self.frame + 1) % self.fences.len();
+		if let Some(fence) = self.fences[self.frame].take() {
+			fence.wait()?;
+		}
+		self.used = 0;
+		Ok(())
+	}
+
+	pub fn buffer(&self) -> &Arc<Buffer<[u8]>> {
+		&self.buffer
+	}
+
+	/// Marks the current frame's region as in use until `fence` signals.
+	pub fn end_frame(&mut self, fence: Arc<Fence>) {
+		self.fences[self.frame] = Some(fence);
+	}
+
+	/// Copies `data` into the current frame's region and returns the dynamic offset to bind it with. Fails with
+	/// `Error::OutOfRingSpace` if the descriptor range at that offset wouldn't fit in the rest of the region. Panics if
+	/// `T` is larger than the ring's range.
+	pub fn push<T: Pod>(&mut self, data: &T) -> Result<u32> {
+		let size = size_of::<T>() as u64;
+		assert!(size <= self.range, "type is larger than the uniform ring's range");
+		let offset = self.used.div_ceil(self.alignment) * self.alignment;
+		// the whole bound range must stay inside the region, not just `data`
+		if offset + self.range > self.frame_size {
+			return Err(Error::OutOfRingSpace);
+		}
+
+		let start = self.frame as u64 * self.frame_size + offset;
+		let bytes = unsafe { slice::from_raw_parts(data as *const T as *const u8, size as usize) };
+		let mut map = self.buffer.map_range_mut(start..start + size)?;
+		map.copy_from_slice(bytes);
+		map.unmap()?;
+
+		self.used = offset + size;
+		// `new` keeps the whole ring within u32 range
+		Ok(u32::try_from(start).unwrap())
+	}
+
+	/// The first `range` bytes of the buffer, to write as a `UNIFORM_BUFFER_DYNAMIC` descriptor that the offsets from
+	/// `push` are added to.
+	pub fn slice(&self) -> BufferSlice<u8> {
+		self.buffer.slice(..self.range)
+	}
+}
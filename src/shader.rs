@@ -1,6 +1,6 @@
 pub use ash::vk::ShaderStageFlags;
 
-use crate::device::Device;
+use crate::{device::Device, error::Result};
 use ash::{version::DeviceV1_0, vk};
 use std::sync::Arc;
 
@@ -9,10 +9,15 @@ pub struct ShaderModule {
 	pub vk: vk::ShaderModule,
 }
 impl ShaderModule {
-	pub unsafe fn new(device: Arc<Device>, code: &[u32]) -> Arc<ShaderModule> {
+	pub unsafe fn new(device: Arc<Device>, code: &[u32]) -> Result<Arc<ShaderModule>> {
 		let ci = vk::ShaderModuleCreateInfo::builder().code(code);
-		let vk = device.vk.create_shader_module(&ci, None).unwrap();
-		Arc::new(Self { device, vk })
+		device.check_lost()?;
+		let vk = device.check(device.vk.create_shader_module(&ci, None))?;
+		Ok(Arc::new(Self { device, vk }))
+	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
 	}
 }
 impl Drop for ShaderModule {
@@ -0,0 +1,277 @@
+use crate::{
+	buffer::{Buffer, BufferAbstract, BufferSlice, CpuOnly, MemoryLocation, Pod},
+	command::{AccessFlags, CommandBufferBuilder, CommandPool, ImageMemoryBarrier, PipelineStageFlags},
+	device::{Device, Queue},
+	error::Result,
+	image::{texel_block, Image, ImageAspectFlags, ImageLayout},
+	sync::{Fence, GpuFuture, Resource, SubmitState},
+};
+use ash::vk;
+use std::{
+	iter::once,
+	mem::size_of_val,
+	slice,
+	sync::Arc,
+};
+use typenum::B0;
+use vk::BufferUsageFlags;
+
+/// Uploads data through a ring of host-visible staging chunks. A chunk is reused once every batch that wrote to it
+/// has finished executing.
+pub struct StagingBelt {
+	device: Arc<Device>,
+	chunk_size: u64,
+	chunks: Vec<Arc<Buffer<[u8]>>>,
+}
+impl StagingBelt {
+	/// Uploads larger than `chunk_size` get a chunk of their own.
+	pub fn new(device: Arc<Device>, chunk_size: u64) -> Self {
+		Self { device, chunk_size, chunks: vec![] }
+	}
+
+	/// Starts recording uploads into one command buffer from `pool`, to be submitted to `queue`.
+	pub fn begin(&mut self, queue: &Arc<Queue>, pool: &Arc<CommandPool>) -> Result<StagingBatch<'_>> {
+		let cmd = pool.record(true, false)?;
+		Ok(StagingBatch { belt: self, queue: queue.clone(), cmd, chunk: None, buffers: vec![], images: vec![] })
+	}
+
+	/// Returns an idle chunk of at least `size` bytes, allocating one if there are none.
+	fn chunk(&mut self, size: u64) -> Result<Arc<Buffer<[u8]>>> {
+		// batches hold their chunks until the GPU is done with them
+		let idle = self.chunks.iter().find(|chunk| Arc::strong_count(chunk) == 1 && chunk.size() >= size);
+		if let Some(chunk) = idle {
+			return Ok(chunk.clone());
+		}
+
+		let size = size.max(self.chunk_size);
+		let chunk = Buffer::new(self.device.clone(), size, BufferUsageFlags::TRANSFER_SRC, CpuOnly::MEMORY_USAGE)?;
+		self.chunks.push(chunk.clone());
+		Ok(chunk)
+	}
+}
+
+/// Uploads recorded by `StagingBelt::begin`.
+pub struct StagingBatch<'a> {
+	belt: &'a mut StagingBelt,
+	queue: Arc<Queue>,
+	cmd: CommandBufferBuilder<B0>,
+	// the chunk being filled, and how many bytes of it are used
+	chunk: Option<(Arc<Buffer<[u8]>>, u64)>,
+	buffers: Vec<(Arc<dyn BufferAbstract + Send + Sync>, u64, u64)>,
+	images: Vec<Arc<Image>>,
+}
+impl StagingBatch<'_> {
+	/// Copies `data` to the start of `dst`. Panics if `data` is longer than `dst`.
+	pub fn buffer<T: Pod>(mut self, dst: impl Into<BufferSlice<T>>, data: &[T]) -> Result<Self> {
+		let dst = dst.into();
+		assert!(data.len() as u64 <= dst.len());
+		if data.is_empty() {
+			return Ok(self);
+		}
+
+		let (chunk, offset) = self.stage(data, 4)?;
+		let size = size_of_val(data) as u64;
+		let region = vk::BufferCopy::builder().src_offset(offset).dst_offset(dst.offset()).size(size).build();
+		self.cmd = self.cmd.copy_buffer_region(chunk, dst.buffer().clone(), region);
+		self.buffers.push((dst.buffer().clone(), dst.offset(), size));
+		Ok(self)
+	}
+
+	/// Copies tightly packed `data` into mip level 0 and layer 0 of `dst`, and leaves it in `SHADER_READ_ONLY_OPTIMAL`.
+	/// `dst` must not have been written to yet. Panics if `data` is too short to fill `dst`, or if the size of `dst`'s
+	/// texels is unknown, as for ASTC and multi-planar formats.
+	pub fn image<T: Pod>(mut self, dst: Arc<Image>, data: &[T]) -> Result<Self> {
+		let size = *dst.size();
+		let block = texel_block(dst.format(), ImageAspectFlags::COLOR);
+		let (block_size, block_width, block_height) = block.expect("unknown texel size");
+		let required = size.x.div_ceil(block_width) as u64 * size.y.div_ceil(block_height) as u64 * size.z as u64;
+		assert!(size_of_val(data) as u64 >= required * block_size, "data is too short to fill the image");
+
+		// buffer-to-image copies need offsets aligned to both 4 and the texel block size
+		let (chunk, offset) = self.stage(data, 4 * block_size)?;
+		let src = chunk.slice(offset..offset + size_of_val(data) as u64);
+		self.cmd = self
+			.cmd
+			.pipeline_barrier(
+				PipelineStageFlags::TOP_OF_PIPE,
+				PipelineStageFlags::TRANSFER,
+				once(ImageMemoryBarrier::new(dst.clone(), ImageLayout::TRANSFER_DST_OPTIMAL)),
+			)
+			.copy_buffer_to_image(src, dst.clone(), &size);
+		self.images.push(dst);
+		Ok(self)
+	}
+
+	/// Submits the batch to the queue it was recorded for.
+	pub fn submit(self) -> Result<StagingFuture> {
+		let queue = self.queue.clone();
+		let cmd = self.visibility_barriers().build()?;
+		Ok(StagingFuture { inner: Box::new(queue.submit(cmd)), _release: None })
+	}
+
+	/// Submits the batch, then makes the uploads available to `dst_queue`. If it is from another queue family,
+	/// ownership of every destination is transferred to it with a command buffer from `dst_pool`.
+	pub fn submit_to(self, dst_queue: &Arc<Queue>, dst_pool: &Arc<CommandPool>) -> Result<StagingFuture> {
+		if self.queue == *dst_queue {
+			return self.submit();
+		}
+
+		let queue = self.queue.clone();
+		let src_family = queue.family().idx;
+		let dst_family = dst_queue.family().idx;
+		let (release, acquire) = if src_family == dst_family {
+			(self.visibility_barriers(), dst_pool.record(true, false)?)
+		} else {
+			self.ownership_barriers(dst_pool, src_family, dst_family)?
+		};
+
+		let (transfer, semaphore) = queue.submit(release.build()?).then_signal_semaphore()?;
+		let release = transfer.then_signal_fence()?;
+		let inner = Box::new(dst_queue.submit_after(semaphore, acquire.build()?));
+		Ok(StagingFuture { inner, _release: Some(release) })
+	}
+
+	/// Writes `data` to a chunk at a multiple of `align` and returns the chunk and offset.
+	fn stage<T: Pod>(&mut self, data: &[T], align: u64) -> Result<(Arc<Buffer<[u8]>>, u64)> {
+		let size = size_of_val(data) as u64;
+		let current = self.chunk.take().map(|(chunk, used)| (chunk, used.div_ceil(align) * align));
+		let (chunk, offset) = match current {
+			Some((chunk, offset)) if offset + size <= chunk.size() => (chunk, offset),
+			_ => (self.belt.chunk(size)?, 0),
+		};
+
+		let bytes = unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, size as usize) };
+		let mut map = chunk.map_range_mut(offset..offset + size)?;
+		map.copy_from_slice(bytes);
+		map.unmap()?;
+
+		self.chunk = Some((chunk.clone(), offset + size));
+		Ok((chunk, offset))
+	}
+
+	/// Barriers making the uploads visible to later commands on the same queue family.
+	fn visibility_barriers(self) -> CommandBufferBuilder<B0> {
+		let mut cmd = self.cmd;
+		if !self.buffers.is_empty() {
+			cmd = cmd.memory_barrier(
+				PipelineStageFlags::TRANSFER,
+				PipelineStageFlags::ALL_COMMANDS,
+				AccessFlags::TRANSFER_WRITE,
+				AccessFlags::MEMORY_READ,
+			);
+		}
+		if !self.images.is_empty() {
+			let barriers = self
+				.images
+				.into_iter()
+				.map(|img| ImageMemoryBarrier::new(img, ImageLayout::SHADER_READ_ONLY_OPTIMAL));
+			cmd = cmd.pipeline_barrier(PipelineStageFlags::TRANSFER, PipelineStageFlags::ALL_COMMANDS, barriers);
+		}
+		cmd
+	}
+
+	/// Release barriers for the batch's command buffer and the matching acquire barriers, recorded from `dst_pool`.
+	fn ownership_barriers(
+		self,
+		dst_pool: &Arc<CommandPool>,
+		src_family: u32,
+		dst_family: u32,
+	) -> Result<(CommandBufferBuilder<B0>, CommandBufferBuilder<B0>)> {
+		let buffer_barrier = |buffer: &Arc<dyn BufferAbstract + Send + Sync>, offset, size| {
+			vk::BufferMemoryBarrier::builder()
+				.src_queue_family_index(src_family)
+				.dst_queue_family_index(dst_family)
+				.buffer(buffer.vk())
+				.offset(offset)
+				.size(size)
+		};
+		let subresource_range = vk::ImageSubresourceRange::builder()
+			.aspect_mask(vk::ImageAspectFlags::COLOR)
+			.level_count(vk::REMAINING_MIP_LEVELS)
+			.layer_count(vk::REMAINING_ARRAY_LAYERS)
+			.build();
+		let image_barrier = |img: &Arc<Image>| {
+			vk::ImageMemoryBarrier::builder()
+				.old_layout(ImageLayout::TRANSFER_DST_OPTIMAL)
+				.new_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+				.src_queue_family_index(src_family)
+				.dst_queue_family_index(dst_family)
+				.image(img.vk)
+				.subresource_range(subresource_range)
+		};
+
+		let release_buffers: Vec<_> = self
+			.buffers
+			.iter()
+			.map(|(buffer, offset, size)| {
+				buffer_barrier(buffer, *offset, *size).src_access_mask(AccessFlags::TRANSFER_WRITE).build()
+			})
+			.collect();
+		let release_images: Vec<_> = self
+			.images
+			.iter()
+			.map(|img| image_barrier(img).src_access_mask(AccessFlags::TRANSFER_WRITE).build())
+			.collect();
+		let acquire_buffers: Vec<_> = self
+			.buffers
+			.iter()
+			.map(|(buffer, offset, size)| {
+				buffer_barrier(buffer, *offset, *size).dst_access_mask(AccessFlags::MEMORY_READ).build()
+			})
+			.collect();
+		let acquire_images: Vec<_> = self
+			.images
+			.iter()
+			.map(|img| image_barrier(img).dst_access_mask(AccessFlags::SHADER_READ).build())
+			.collect();
+
+		for img in &self.images {
+			img.set_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+		}
+
+		let release = self.cmd.barrier(
+			PipelineStageFlags::TRANSFER,
+			PipelineStageFlags::BOTTOM_OF_PIPE,
+			&release_buffers,
+			&release_images,
+			None,
+		);
+		let resources = self
+			.buffers
+			.into_iter()
+			.map(|(buffer, ..)| Resource::Buffer(buffer))
+			.chain(self.images.into_iter().map(Resource::Image));
+		let acquire = dst_pool.record(true, false)?.barrier(
+			PipelineStageFlags::ALL_COMMANDS,
+			PipelineStageFlags::ALL_COMMANDS,
+			&acquire_buffers,
+			&acquire_images,
+			resources,
+		);
+		Ok((release, acquire))
+	}
+}
+
+/// Completes once a batch's uploads are visible to the queue it was submitted to.
+pub struct StagingFuture {
+	inner: Box<dyn GpuFuture + Send + Sync>,
+	// the transfer queue's half of an ownership transfer
+	_release: Option<Fence>,
+}
+impl GpuFuture for StagingFuture {
+	fn build_submission(&mut self) -> SubmitState {
+		self.inner.build_submission()
+	}
+
+	fn device(&self) -> &Arc<Device> {
+		self.inner.device()
+	}
+
+	fn flush(&mut self) {
+		self.inner.flush()
+	}
+
+	fn queue(&self) -> Option<&Arc<Queue>> {
+		self.inner.queue()
+	}
+}
@@ -3,10 +3,13 @@ pub use ash::vk::{
 	SurfaceFormatKHR as SurfaceFormat, SurfaceTransformFlagsKHR as SurfaceTransformFlags,
 };
 
-use crate::instance::Instance;
-use ash::vk;
+use crate::{
+	error::{Error, Result},
+	instance::Instance,
+};
+use ash::{extensions::khr, version::InstanceV1_0, vk};
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
-use std::sync::Arc;
+use std::{ffi::CStr, ptr, sync::Arc};
 
 pub struct Surface<T> {
 	instance: Arc<Instance>,
@@ -14,27 +17,58 @@ pub struct Surface<T> {
 	pub vk: vk::SurfaceKHR,
 }
 impl<T: HasRawWindowHandle> Surface<T> {
-	pub fn new(instance: Arc<Instance>, window: T) -> Arc<Surface<T>> {
+	/// Fails with `Error::UnsupportedWindowHandle` on platforms other than Win32, Xlib and Wayland, and with
+	/// `Error::MissingExtensions` if the platform's surface extension was not enabled on `instance`.
+	pub fn new(instance: Arc<Instance>, window: T) -> Result<Arc<Surface<T>>> {
+		if !instance.is_extension_enabled(khr::Surface::name()) {
+			return Err(missing(khr::Surface::name()));
+		}
+
 		let vk = match window.raw_window_handle() {
 			#[cfg(windows)]
 			RawWindowHandle::Windows(handle) => {
 				let ci = vk::Win32SurfaceCreateInfoKHR::builder().hinstance(handle.hinstance).hwnd(handle.hwnd);
-				unsafe { instance.khr_win32_surface.create_win32_surface(&ci, None) }.unwrap()
+				let khr_win32_surface =
+					instance.khr_win32_surface.as_ref().ok_or_else(|| missing(khr::Win32Surface::name()))?;
+				unsafe { khr_win32_surface.create_win32_surface(&ci, None) }?
 			},
 			#[cfg(unix)]
 			RawWindowHandle::Xlib(handle) => {
 				let ci = vk::XlibSurfaceCreateInfoKHR::builder().dpy(handle.display as _).window(handle.window);
-				unsafe { instance.khr_xlib_surface.create_xlib_surface(&ci, None) }.unwrap()
+				let khr_xlib_surface =
+					instance.khr_xlib_surface.as_ref().ok_or_else(|| missing(khr::XlibSurface::name()))?;
+				unsafe { khr_xlib_surface.create_xlib_surface(&ci, None) }?
 			},
 			#[cfg(unix)]
 			RawWindowHandle::Wayland(handle) => {
 				let ci = vk::WaylandSurfaceCreateInfoKHR::builder().display(handle.display).surface(handle.surface);
-				unsafe { instance.khr_wayland_surface.create_wayland_surface(&ci, None) }.unwrap()
+				let khr_wayland_surface =
+					instance.khr_wayland_surface.as_ref().ok_or_else(|| missing(khr::WaylandSurface::name()))?;
+				unsafe { khr_wayland_surface.create_wayland_surface(&ci, None) }?
 			},
-			_ => unimplemented!(),
+			_ => return Err(Error::UnsupportedWindowHandle),
 		};
 
-		Arc::new(Self { instance, window, vk })
+		Ok(Arc::new(Self { instance, window, vk }))
+	}
+}
+impl Surface<()> {
+	/// Creates a surface backed by `VK_EXT_headless_surface`, which can be presented to without a window.
+	pub fn headless(instance: Arc<Instance>) -> Result<Arc<Surface<()>>> {
+		let ext_headless_surface = instance.ext_headless_surface.as_ref().ok_or_else(|| {
+			Error::MissingExtensions(vec![vk::ExtHeadlessSurfaceFn::name().to_string_lossy().into_owned()])
+		})?;
+
+		let ci = vk::HeadlessSurfaceCreateInfoEXT::builder();
+		let mut vk = vk::SurfaceKHR::null();
+		let res = unsafe {
+			ext_headless_surface.create_headless_surface_ext(instance.vk.handle(), &*ci, ptr::null(), &mut vk)
+		};
+		if res != vk::Result::SUCCESS {
+			return Err(res.into());
+		}
+
+		Ok(Arc::new(Self { instance, window: (), vk }))
 	}
 }
 impl<T> Surface<T> {
@@ -47,3 +81,7 @@ impl<T> Drop for Surface<T> {
 		unsafe { self.instance.khr_surface.destroy_surface(self.vk, None) };
 	}
 }
+
+fn missing(extension: &CStr) -> Error {
+	Error::MissingExtensions(vec![extension.to_string_lossy().into_owned()])
+}
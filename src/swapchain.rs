@@ -8,11 +8,12 @@ pub use ash::vk::CompositeAlphaFlagsKHR as CompositeAlphaFlags;
 
 use crate::{
 	device::Device,
+	error::{Error, Result},
 	image::Format,
 	surface::{ColorSpace, PresentMode, Surface, SurfaceTransformFlags},
 	Extent2D,
 };
-use ash::vk;
+use ash::{extensions::khr, vk};
 use std::sync::Arc;
 
 trait SwapchainAbstract {
@@ -37,7 +38,11 @@ impl<T: Send + Sync + 'static> Swapchain<T> {
 		composite_alpha: CompositeAlphaFlags,
 		present_mode: PresentMode,
 		old_swapchain: Option<&Self>,
-	) -> (Arc<Self>, impl Iterator<Item = Arc<SwapchainImage>>) {
+	) -> Result<(Arc<Self>, impl Iterator<Item = Arc<SwapchainImage>>)> {
+		if !device.is_extension_enabled(khr::Swapchain::name()) {
+			return Err(Error::MissingExtensions(vec![khr::Swapchain::name().to_string_lossy().into_owned()]));
+		}
+
 		let queue_family_indices: Vec<_> = queue_families
 			.into_iter()
 			.inspect(|qfam| assert!(device.physical_device() == qfam.physical_device()))
@@ -62,21 +67,22 @@ impl<T: Send + Sync + 'static> Swapchain<T> {
 			.present_mode(present_mode)
 			.clipped(true)
 			.old_swapchain(old_swapchain.map(|x| x.vk).unwrap_or(vk::SwapchainKHR::null()));
-		let vk = unsafe { device.khr_swapchain.create_swapchain(&ci, None) }.unwrap();
-		let images = unsafe { device.khr_swapchain.get_swapchain_images(vk) }.unwrap();
-
+		device.check_lost()?;
+		let vk = device.check(unsafe { device.khr_swapchain.create_swapchain(&ci, None) })?;
 		let swapchain = unsafe { Swapchain::from_vk(device.clone(), surface, vk) };
+		let images = device.check(unsafe { device.khr_swapchain.get_swapchain_images(vk) })?;
 
 		let swapchain2 = swapchain.clone();
 		let images = images.into_iter().map(move |vk| Arc::new(SwapchainImage { swapchain: swapchain2.clone(), vk }));
 
-		(swapchain, images)
+		Ok((swapchain, images))
 	}
 
-	pub fn acquire_next_image(self: &Arc<Self>, timeout: u64) -> Result<(u32, bool, AcquireFuture<T>), vk::Result> {
-		let semaphore = Semaphore::new(self.device.clone());
-		let (image_idx, suboptimal) =
-			unsafe { self.device.khr_swapchain.acquire_next_image(self.vk, timeout, semaphore.vk, vk::Fence::null()) }?;
+	pub fn acquire_next_image(self: &Arc<Self>, timeout: u64) -> Result<(u32, bool, AcquireFuture<T>)> {
+		let semaphore = Semaphore::new(self.device.clone())?;
+		let res =
+			unsafe { self.device.khr_swapchain.acquire_next_image(self.vk, timeout, semaphore.vk, vk::Fence::null()) };
+		let (image_idx, suboptimal) = self.device.check(res)?;
 		Ok((image_idx, suboptimal, AcquireFuture { _swapchain: self.clone(), semaphore }))
 	}
 
@@ -85,7 +91,7 @@ impl<T: Send + Sync + 'static> Swapchain<T> {
 		queue: Arc<Queue>,
 		swapchains: &[Arc<Swapchain<T>>],
 		image_indices: &[u32],
-	) -> Result<bool, vk::Result> {
+	) -> Result<bool> {
 		let semaphore_vks: Vec<_> = semaphores.iter().map(|x| x.semaphore().vk).collect();
 		let swapchain_vks: Vec<_> = swapchains.iter().map(|x| x.vk).collect();
 
@@ -94,12 +100,13 @@ impl<T: Send + Sync + 'static> Swapchain<T> {
 			.wait_semaphores(&semaphore_vks)
 			.swapchains(&swapchain_vks)
 			.image_indices(image_indices);
-		let suboptimal = unsafe { queue.device.khr_swapchain.queue_present(queue.vk, &ci) }?;
+		queue.device.check_lost()?;
+		let suboptimal = queue.device.check(unsafe { queue.device.khr_swapchain.queue_present(queue.vk, &ci) })?;
 
 		Ok(suboptimal)
 	}
 
-	pub fn recreate<'a>(
+	pub fn recreate(
 		&self,
 		min_image_count: u32,
 		image_format: Format,
@@ -109,7 +116,7 @@ impl<T: Send + Sync + 'static> Swapchain<T> {
 		pre_transform: SurfaceTransformFlags,
 		composite_alpha: CompositeAlphaFlags,
 		present_mode: PresentMode,
-	) -> (Arc<Swapchain<T>>, impl Iterator<Item = Arc<SwapchainImage>>) {
+	) -> Result<(Arc<Swapchain<T>>, impl Iterator<Item = Arc<SwapchainImage>>)> {
 		let queue_family_indices: Vec<_> = queue_families
 			.into_iter()
 			.inspect(|qfam| assert!(self.device.physical_device() == qfam.physical_device()))
@@ -134,14 +141,15 @@ impl<T: Send + Sync + 'static> Swapchain<T> {
 			.present_mode(present_mode)
 			.clipped(true)
 			.old_swapchain(self.vk);
-		let vk = unsafe { self.device.khr_swapchain.create_swapchain(&ci, None) }.unwrap();
-		let images = unsafe { self.device.khr_swapchain.get_swapchain_images(vk) }.unwrap();
-
+		self.device.check_lost()?;
+		let vk = self.device.check(unsafe { self.device.khr_swapchain.create_swapchain(&ci, None) })?;
 		let swapchain = unsafe { Swapchain::from_vk(self.device.clone(), self.surface.clone(), vk) };
+		let images = self.device.check(unsafe { self.device.khr_swapchain.get_swapchain_images(vk) })?;
+
 		let swapchain2 = swapchain.clone();
 		let images = images.into_iter().map(move |vk| Arc::new(SwapchainImage { swapchain: swapchain2.clone(), vk }));
 
-		(swapchain, images)
+		Ok((swapchain, images))
 	}
 
 	pub fn surface(&self) -> &Arc<Surface<T>> {
@@ -169,7 +177,7 @@ pub struct SwapchainImage {
 }
 impl ImageAbstract for SwapchainImage {
 	fn device(&self) -> &Arc<Device> {
-		&self.swapchain.device()
+		self.swapchain.device()
 	}
 
 	fn vk(&self) -> vk::Image {
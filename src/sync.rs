@@ -1,10 +1,11 @@
 use crate::{
-	buffer::BufferAbstract,
+	buffer::{BufferAbstract, BufferView},
 	command::CommandBuffer,
 	descriptor::DescriptorSet,
 	device::{Device, Queue},
+	error::Result,
 	image::{Framebuffer, Image, ImageView, Sampler},
-	pipeline::{GraphicsPipeline, PipelineLayout},
+	pipeline::{ComputePipeline, GraphicsPipeline, PipelineLayout},
 	render_pass::RenderPass,
 };
 use ash::{version::DeviceV1_0, vk};
@@ -17,12 +18,17 @@ pub struct Semaphore {
 	pub vk: vk::Semaphore,
 }
 impl Semaphore {
-	pub(crate) fn new(device: Arc<Device>) -> Arc<Semaphore> {
+	pub(crate) fn new(device: Arc<Device>) -> Result<Arc<Semaphore>> {
 		unsafe {
-			let vk = device.vk.create_semaphore(&vk::SemaphoreCreateInfo::builder(), None).unwrap();
-			Arc::new(Self { device, vk })
+			device.check_lost()?;
+			let vk = device.check(device.vk.create_semaphore(&vk::SemaphoreCreateInfo::builder(), None))?;
+			Ok(Arc::new(Self { device, vk }))
 		}
 	}
+
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
+	}
 }
 impl Drop for Semaphore {
 	fn drop(&mut self) {
@@ -43,19 +49,19 @@ pub trait GpuFuture {
 		JoinFuture::new(self, other)
 	}
 
-	fn then_signal_fence(self) -> Fence
+	fn then_signal_fence(self) -> Result<Fence>
 	where
 		Self: Send + Sync + Sized + 'static,
 	{
 		Fence::end(self)
 	}
 
-	fn then_signal_semaphore(self) -> (SemaphoreSignalFuture<Self>, SemaphoreFuture)
+	fn then_signal_semaphore(self) -> Result<(SemaphoreSignalFuture<Self>, SemaphoreFuture)>
 	where
 		Self: Sized,
 	{
-		let semaphore = Semaphore::new(self.device().clone());
-		(SemaphoreSignalFuture::new(self, semaphore.clone()), SemaphoreFuture::new(semaphore))
+		let semaphore = Semaphore::new(self.device().clone())?;
+		Ok((SemaphoreSignalFuture::new(self, semaphore.clone()), SemaphoreFuture::new(semaphore)))
 	}
 }
 impl GpuFuture for Box<dyn GpuFuture> {
@@ -122,7 +128,9 @@ impl SemaphoreFuture {
 }
 impl GpuFuture for SemaphoreFuture {
 	fn build_submission(&mut self) -> SubmitState {
-		todo!()
+		let mut submit = SubmitState::new();
+		submit.wait_semaphore(&self.semaphore, vk::PipelineStageFlags::ALL_COMMANDS);
+		submit
 	}
 
 	fn device(&self) -> &Arc<Device> {
@@ -173,16 +181,19 @@ pub struct Fence {
 	vk: vk::Fence,
 }
 impl Fence {
-	pub fn new(device: &Arc<Device>, signalled: bool) -> Self {
+	pub fn new(device: &Arc<Device>, signalled: bool) -> Result<Self> {
 		let flags = if signalled { vk::FenceCreateFlags::SIGNALED } else { vk::FenceCreateFlags::empty() };
-		let vk = unsafe { device.vk.create_fence(&vk::FenceCreateInfo::builder().flags(flags), None) }.unwrap();
-		Self { device: device.clone(), prev: AtomicCell::default(), vk }
+		device.check_lost()?;
+		let vk = device.check(unsafe { device.vk.create_fence(&vk::FenceCreateInfo::builder().flags(flags), None) })?;
+		Ok(Self { device: device.clone(), prev: AtomicCell::default(), vk })
 	}
 
-	pub fn end(mut prev: impl GpuFuture + Send + Sync + 'static) -> Self {
+	pub fn end(mut prev: impl GpuFuture + Send + Sync + 'static) -> Result<Self> {
+		prev.device().check_lost()?;
+
 		let submit = prev.build_submission();
 
-		let vk = unsafe { prev.device().vk.create_fence(&vk::FenceCreateInfo::builder(), None) }.unwrap();
+		let vk = prev.device().check(unsafe { prev.device().vk.create_fence(&vk::FenceCreateInfo::builder(), None) })?;
 
 		let submits = [vk::SubmitInfo::builder()
 			.wait_semaphores(&submit.wait_semaphores)
@@ -190,19 +201,32 @@ impl Fence {
 			.signal_semaphores(&submit.signal_semaphores)
 			.command_buffers(&submit.cmds)
 			.build()];
-		unsafe { prev.device().vk.queue_submit(prev.queue().unwrap().vk, &submits, vk) }.unwrap();
+		let res = unsafe { prev.device().vk.queue_submit(prev.queue().unwrap().vk, &submits, vk) };
+		prev.device().check(res).inspect_err(|_| unsafe { prev.device().vk.destroy_fence(vk, None) })?;
+
+		Ok(Self { device: prev.device().clone(), prev: AtomicCell::new(Some(Box::new(prev))), vk })
+	}
 
-		Self { device: prev.device().clone(), prev: AtomicCell::new(Some(Box::new(prev))), vk }
+	pub fn set_name(&self, name: &str) -> Result<()> {
+		self.device.set_object_name(self.vk, name)
 	}
 
-	pub fn wait(&self) {
-		unsafe { self.device.vk.wait_for_fences(&[self.vk], false, !0) }.unwrap();
-		self.prev.take();
+	/// Returns immediately with `Error::DeviceLost` once the device is lost, since the fence may never signal.
+	pub fn wait(&self) -> Result<()> {
+		let res = self
+			.device
+			.check_lost()
+			.and_then(|_| self.device.check(unsafe { self.device.vk.wait_for_fences(&[self.vk], false, !0) }));
+		// a lost device has stopped executing, so the resources are safe to release either way
+		if res.is_ok() || self.device.is_lost() {
+			self.prev.take();
+		}
+		res
 	}
 }
 impl Drop for Fence {
 	fn drop(&mut self) {
-		self.wait();
+		let _ = self.wait();
 		unsafe { self.device.vk.destroy_fence(self.vk, None) };
 	}
 }
@@ -267,10 +291,13 @@ impl GpuFuture for NowFuture {
 }
 
 #[derive(Clone)]
+#[allow(dead_code)]
 pub(crate) enum Resource {
 	Buffer(Arc<dyn BufferAbstract + Send + Sync>),
+	BufferView(Arc<BufferView>),
 	// TODO: merge with CommandBufferAbstract trait?
 	CommandBufferSecondary(Arc<CommandBuffer<B1>>),
+	ComputePipeline(Arc<ComputePipeline>),
 	DescriptorSet(Arc<DescriptorSet>),
 	Framebuffer(Arc<Framebuffer>),
 	Image(Arc<Image>),